@@ -6,14 +6,21 @@ extern crate rand;
 use atty::Stream;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use std::io::{self, BufRead};
+use std::process;
 
-use git_release_name::Case;
+use git_release_name::{Case, Phrase, RandomShas};
 
 fn main() {
     let matches = app_matches();
 
     if let Some(matches) = matches.subcommand_matches("list") {
         list::list_dictionary(matches);
+    } else if let Some(matches) = matches.subcommand_matches("collisions") {
+        collisions::report(matches);
+    } else if let Some(matches) = matches.subcommand_matches("file") {
+        from_files(matches);
+    } else if let Some(matches) = matches.subcommand_matches("allocate") {
+        allocate::allocate(matches);
     } else {
         let format = if let Some(fmt) = matches.value_of("format") {
             fmt.parse().expect("Invalid format specified")
@@ -21,25 +28,31 @@ fn main() {
             Case::Lower
         };
 
+        let hash = matches.is_present("hash");
+
+        let count = matches
+            .value_of("count")
+            .map(|count| count.parse().expect("Invalid count specified"));
+        let seed = matches
+            .value_of("seed")
+            .map(|seed| seed.parse().expect("Invalid seed specified"));
+
         if let Some(shas) = matches.values_of("SHA") {
-            shas.for_each(|sha| {
-                println!(
-                    "{}",
-                    git_release_name::lookup(&sha)
-                        .expect("Invalid sha")
-                        .with_case(format)
-                )
-            });
-        } else if atty::is(Stream::Stdin) {
-            from_random_sha(format)
+            shas.for_each(|sha| println!("{}", name(sha, hash).with_case(format)));
+        } else if atty::is(Stream::Stdin) || count.is_some() || seed.is_some() {
+            from_random_shas(
+                format,
+                seed.unwrap_or_else(rand::random),
+                count.unwrap_or(1),
+            )
         } else {
             // no args, check stdin
-            from_stdin(format);
+            from_stdin(format, hash);
         };
     }
 }
 
-const FORMAT_OPTIONS: [&'static str; 8] = [
+const FORMAT_OPTIONS: [&str; 8] = [
     "snake", "kebab", "camel", "pascal", "title", "sentence", "upper", "lower",
 ];
 
@@ -71,6 +84,88 @@ fn app_matches() -> ArgMatches<'static> {
                         .help("Specify the row format to use"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("collisions")
+                .about("Scan a repository's history for commits that share a release name.")
+                .arg(
+                    Arg::with_name("tags")
+                        .long("tags")
+                        .short("t")
+                        .help("Only scan commits that are pointed to by a tag."),
+                )
+                .arg(
+                    Arg::with_name("repo")
+                        .long("repo")
+                        .short("C")
+                        .takes_value(true)
+                        .help("Path to the repository to scan. Defaults to the current directory."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("file")
+                .about("Name files by their contents, the same way git would store them.")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .short("f")
+                        .takes_value(true)
+                        .possible_values(&FORMAT_OPTIONS)
+                        .help("Declares the return format of the phrase."),
+                )
+                .arg(
+                    Arg::with_name("PATH")
+                        .multiple(true)
+                        .required(true)
+                        .help("Each arg should be the path to a file."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("allocate")
+                .about("Generate names that aren't already in use.")
+                .arg(
+                    Arg::with_name("taken")
+                        .long("taken")
+                        .short("t")
+                        .takes_value(true)
+                        .help(
+                            "A file of names already in use, one per line. Use - to read them \
+                             from STDIN.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .long("count")
+                        .short("n")
+                        .takes_value(true)
+                        .help("How many names to generate."),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .help("Seed the names so the same names are generated every time."),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .short("f")
+                        .takes_value(true)
+                        .possible_values(&FORMAT_OPTIONS)
+                        .help("Declares the return format of the phrase."),
+                )
+                .arg(
+                    Arg::with_name("max-length")
+                        .long("max-length")
+                        .takes_value(true)
+                        .help("Only generate names with at most this many characters."),
+                )
+                .arg(
+                    Arg::with_name("initial")
+                        .long("initial")
+                        .takes_value(true)
+                        .help("Only generate names starting with this letter."),
+                ),
+        )
         .arg(
             Arg::with_name("format")
                 .long("format")
@@ -80,6 +175,28 @@ fn app_matches() -> ArgMatches<'static> {
                 .alias("f")
                 .help("Declares the return format of the phrase."),
         )
+        .arg(Arg::with_name("hash").long("hash").help(
+            "Hash each input before naming it so that any text, such as a branch name or \
+                     build number, can be used instead of a sha.",
+        ))
+        .arg(
+            Arg::with_name("count")
+                .long("count")
+                .short("n")
+                .takes_value(true)
+                .conflicts_with("SHA")
+                .help("Generate this many random names."),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .conflicts_with("SHA")
+                .help(
+                    "Seed the random names so the same seed always gives the same names. \
+                     Any number from 0 to 18446744073709551615 may be used.",
+                ),
+        )
         .arg(Arg::with_name("bench").long("bench"))
         .arg(Arg::with_name("SHA").multiple(true).help(
             "Each arg should be a sha. If they are less than 8 characters they will be padded",
@@ -151,6 +268,7 @@ mod list {
         }
     }
 
+    #[allow(clippy::print_literal)]
     fn print_fixed(entries: &[Entry]) {
         println!(
             "{kind:>4} {word:<20} {index}",
@@ -173,6 +291,7 @@ mod list {
         }
     }
 
+    #[allow(clippy::print_literal)]
     fn print_csv(entries: &[Entry]) {
         println!(
             "{kind},{word},{index}",
@@ -196,27 +315,194 @@ mod list {
     }
 }
 
-fn from_random_sha(format: Case) {
-    println!(
-        "{}",
-        git_release_name::lookup(&format!("{:08x}", rand::random::<u32>()))
-            .unwrap()
-            .with_case(format)
-    );
+mod allocate {
+    use clap::ArgMatches;
+    use git_release_name::{Allocator, Case, Constraints};
+    use rand;
+    use std::fs::File;
+    use std::io::{self, BufRead, BufReader};
+    use std::process;
+
+    pub fn allocate(matches: &ArgMatches) {
+        let constraints = Constraints {
+            case: matches.value_of("format").map_or(Case::Lower, |fmt| {
+                fmt.parse().expect("Invalid format specified")
+            }),
+            max_length: matches
+                .value_of("max-length")
+                .map(|max| max.parse().expect("Invalid max length specified")),
+            initial: matches.value_of("initial").map(|initial| {
+                let mut chars = initial.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => panic!("Invalid initial specified"),
+                }
+            }),
+        };
+        let count = matches
+            .value_of("count")
+            .map_or(1, |count| count.parse().expect("Invalid count specified"));
+        let seed = matches.value_of("seed").map_or_else(rand::random, |seed| {
+            seed.parse().expect("Invalid seed specified")
+        });
+
+        let taken = match matches.value_of("taken") {
+            Some(path) => taken(path).unwrap_or_else(|err| {
+                eprintln!("{}: {}", path, err);
+                process::exit(1);
+            }),
+            None => Vec::new(),
+        };
+
+        let mut allocator = Allocator::new(taken, constraints, seed);
+        for _ in 0..count {
+            match allocator.next() {
+                Some(allocation) => println!("{}", allocation.phrase),
+                None => {
+                    eprintln!("No free name fits the constraints");
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    /// Reads the names already in use, skipping blank lines.
+    fn taken(path: &str) -> io::Result<Vec<String>> {
+        let lines: Vec<String> = if path == "-" {
+            io::stdin().lock().lines().collect::<io::Result<_>>()?
+        } else {
+            BufReader::new(File::open(path)?)
+                .lines()
+                .collect::<io::Result<_>>()?
+        };
+        Ok(lines
+            .into_iter()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect())
+    }
+}
+
+mod collisions {
+    use clap::ArgMatches;
+    use git_release_name::stats;
+    use std::collections::BTreeMap;
+    use std::process::{self, Command};
+
+    pub fn report(matches: &ArgMatches) {
+        let shas = rev_list(matches.value_of("repo"), matches.is_present("tags"));
+
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for sha in &shas {
+            let name = git_release_name::lookup(sha)
+                .expect("Invalid sha")
+                .to_string();
+            groups.entry(name).or_default().push(sha.to_string());
+        }
+
+        let mut collisions: Vec<(&String, &Vec<String>)> =
+            groups.iter().filter(|&(_, shas)| shas.len() > 1).collect();
+        collisions.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+
+        let n = shas.len() as u64;
+        println!(
+            "Scanned {} {}, {} unique names",
+            shas.len(),
+            if matches.is_present("tags") {
+                "tagged commits"
+            } else {
+                "commits"
+            },
+            groups.len()
+        );
+        println!(
+            "Estimated collision probability: {:.6}% ({:.6} expected colliding pairs)",
+            stats::collision_probability(n) * 100.0,
+            stats::expected_collisions(n)
+        );
+
+        if collisions.is_empty() {
+            println!("No collisions found.");
+            return;
+        }
+
+        println!("Found {} colliding names:", collisions.len());
+        for (name, shas) in collisions {
+            println!("{:>4} {}", shas.len(), name);
+            for sha in shas {
+                println!("     {}", sha);
+            }
+        }
+    }
+
+    fn rev_list(repo: Option<&str>, tags: bool) -> Vec<String> {
+        let mut cmd = Command::new("git");
+        if let Some(repo) = repo {
+            cmd.arg("-C").arg(repo);
+        }
+        cmd.arg("rev-list");
+        if tags {
+            cmd.args(["--no-walk", "--tags"]);
+        } else {
+            cmd.arg("--all");
+        }
+
+        let output = cmd.output().expect("Unable to run git");
+        if !output.status.success() {
+            eprint!("{}", String::from_utf8_lossy(&output.stderr));
+            process::exit(1);
+        }
+
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+}
+
+fn from_files(matches: &ArgMatches) {
+    let format = if let Some(fmt) = matches.value_of("format") {
+        fmt.parse().expect("Invalid format specified")
+    } else {
+        Case::Lower
+    };
+
+    for path in matches.values_of("PATH").into_iter().flatten() {
+        match git_release_name::name_for_file(path) {
+            Ok(name) => println!("{}", name.with_case(format)),
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn from_random_shas(format: Case, seed: u64, count: usize) {
+    for sha in RandomShas::new(seed).take(count) {
+        println!(
+            "{}",
+            git_release_name::lookup(&sha).unwrap().with_case(format)
+        );
+    }
+}
+
+fn name(input: &str, hash: bool) -> Phrase {
+    if hash {
+        git_release_name::lookup_hashed(input)
+    } else {
+        git_release_name::lookup(input).expect("Invalid sha")
+    }
 }
 
-fn from_stdin(format: Case) {
+fn from_stdin(format: Case, hash: bool) {
     let stdin = io::stdin();
     let mut reader = stdin.lock();
     loop {
         let mut line = String::new();
         match reader.read_line(&mut line) {
-            Ok(size) if size > 0 => println!(
-                "{}",
-                git_release_name::lookup(&line.trim())
-                    .unwrap()
-                    .with_case(format)
-            ),
+            Ok(size) if size > 0 => println!("{}", name(line.trim(), hash).with_case(format)),
             _ => break,
         }
     }
@@ -9,6 +9,32 @@ mod integration {
         Assert::main_binary().succeeds().unwrap();
     }
 
+    #[test]
+    fn it_can_generate_seeded_random_names() {
+        Assert::main_binary()
+            .with_args(&["--seed", "0", "-n", "2", "--format", "kebab"])
+            .succeeds()
+            .stdout()
+            .is("forcibly-endways-holdback\nunpeacefully-costly-protest")
+            .unwrap();
+    }
+
+    #[test]
+    fn it_can_allocate_names_that_are_not_taken() {
+        Assert::main_binary()
+            .with_args(&[
+                "allocate",
+                "--seed",
+                "0",
+                "--taken",
+                "tests/fixtures/taken.txt",
+            ])
+            .succeeds()
+            .stdout()
+            .is("unpeacefully costly protest")
+            .unwrap();
+    }
+
     #[test]
     fn it_can_generate_a_name_based_on_a_sha() {
         Assert::main_binary()
@@ -67,6 +93,37 @@ mod integration {
             .unwrap();
     }
 
+    #[test]
+    fn it_can_generate_a_name_based_on_hashed_input() {
+        Assert::main_binary()
+            .with_args(&["--hash", "abc"])
+            .succeeds()
+            .stdout()
+            .contains("peartly gracious grouters")
+            .unwrap();
+        Assert::main_binary()
+            .with_args(&["--hash", "-f", "kebab"])
+            .stdin("main")
+            .succeeds()
+            .stdout()
+            .contains("deferentially-earthen-naphthene")
+            .unwrap();
+    }
+
+    #[test]
+    fn it_can_generate_a_name_based_on_file_contents() {
+        Assert::main_binary()
+            .with_args(&["file", "tests/fixtures/hello.txt"])
+            .succeeds()
+            .stdout()
+            .contains("impatiently fretty jeerer")
+            .unwrap();
+        Assert::main_binary()
+            .with_args(&["file", "tests/fixtures/missing.txt"])
+            .fails()
+            .unwrap();
+    }
+
     #[test]
     fn formats_sets_of_words() {
         Assert::main_binary()
@@ -95,6 +152,22 @@ mod integration {
             .unwrap();
     }
 
+    #[test]
+    fn reports_collisions_in_history() {
+        Assert::main_binary()
+            .with_args(&["collisions"])
+            .succeeds()
+            .stdout()
+            .contains("Estimated collision probability")
+            .unwrap();
+        Assert::main_binary()
+            .with_args(&["collisions", "--tags"])
+            .succeeds()
+            .stdout()
+            .contains("tagged commits")
+            .unwrap();
+    }
+
     #[test]
     fn lists_sets_of_words() {
         macro_rules! test_list_command {
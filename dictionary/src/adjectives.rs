@@ -1,4 +1,4 @@
-pub const WORDS: [&'static str; 256] = [
+pub const WORDS: [&str; 256] = [
     "timeless",
     "courant",
     "twinning",
@@ -1,4 +1,5 @@
-pub const WORDS: [&'static str; 4096] = [
+#[allow(clippy::large_const_arrays)]
+pub const WORDS: [&str; 4096] = [
     "ambitiously",
     "exaggeratedly",
     "disjunctively",
@@ -0,0 +1,245 @@
+use case::Case;
+use phrase::Phrase;
+use random::RandomShas;
+use std::cmp;
+use std::collections::HashSet;
+
+/// How many shas are tried for each name before giving up. The constraints
+/// can rule out most names, but not so many that this many tries would
+/// normally fail.
+const MAX_ATTEMPTS: usize = 100_000;
+
+/// Restrictions on the names an `Allocator` hands out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constraints {
+    /// The case names are formatted in, which also decides their length
+    pub case: Case,
+    /// The longest name, in characters, once formatted
+    pub max_length: Option<usize>,
+    /// The letter every name must start with, ignoring case
+    pub initial: Option<char>,
+}
+
+impl Default for Constraints {
+    fn default() -> Constraints {
+        Constraints {
+            case: Case::Lower,
+            max_length: None,
+            initial: None,
+        }
+    }
+}
+
+impl Constraints {
+    fn allows(&self, name: &str) -> bool {
+        self.max_length
+            .is_none_or(|max| name.chars().count() <= max)
+            && self.initial.is_none_or(|initial| {
+                name.chars()
+                    .next()
+                    .is_some_and(|c| c.to_lowercase().eq(initial.to_lowercase()))
+            })
+    }
+}
+
+/// A name handed out by an `Allocator` and the sha it was formed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    pub sha: String,
+    pub phrase: Phrase,
+}
+
+/// Hands out names that aren't already taken.
+///
+/// Candidates come from `RandomShas`, so the same seed, taken names and
+/// constraints always give the same names. Names are compared ignoring case
+/// and separators, so `timeless-gemot` and `TimelessGemot` are the same
+/// name. Every name handed out is added to the taken names, so the
+/// allocator never gives out a name twice.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::{Allocator, Constraints};
+///
+/// let taken = vec!["forcibly endways holdback"];
+/// let mut allocator = Allocator::new(taken, Constraints::default(), 0);
+/// let allocation = allocator.next().unwrap();
+/// assert_eq!(allocation.phrase.to_string(), "unpeacefully costly protest");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Allocator {
+    taken: HashSet<String>,
+    constraints: Constraints,
+    shas: RandomShas,
+    attempts: usize,
+    out_of_attempts: bool,
+}
+
+/// Reduces a name to its lower case letters and digits, which is how names
+/// are compared when deciding whether one is taken.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::normalize_name;
+///
+/// assert_eq!(normalize_name("Timeless-Gemot"), "timelessgemot");
+/// ```
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl Allocator {
+    pub fn new<I, S>(taken: I, constraints: Constraints, seed: u64) -> Allocator
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Allocator {
+            taken: taken
+                .into_iter()
+                .map(|name| normalize_name(name.as_ref()))
+                .collect(),
+            constraints,
+            shas: RandomShas::new(seed),
+            attempts: usize::MAX,
+            out_of_attempts: false,
+        }
+    }
+
+    /// Limits how many shas are tried in total, for every name handed out
+    /// together, rather than only for each name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use git_release_name::{Allocator, Constraints};
+    ///
+    /// let taken: Vec<String> = vec![];
+    /// let mut allocator = Allocator::new(taken, Constraints::default(), 0).with_max_attempts(2);
+    /// assert_eq!(allocator.by_ref().count(), 2);
+    /// assert!(allocator.ran_out_of_attempts());
+    /// ```
+    pub fn with_max_attempts(mut self, attempts: usize) -> Allocator {
+        self.attempts = attempts;
+        self
+    }
+
+    /// Whether the allocator stopped handing out names because the limit
+    /// set with `with_max_attempts` was used up, rather than because no
+    /// free name fits the constraints.
+    pub fn ran_out_of_attempts(&self) -> bool {
+        self.out_of_attempts
+    }
+
+    /// Whether a name is already taken.
+    pub fn is_taken(&self, name: &str) -> bool {
+        self.taken.contains(&normalize_name(name))
+    }
+}
+
+impl Iterator for Allocator {
+    type Item = Allocation;
+
+    /// Finds the next free name, or `None` if no free name that fits the
+    /// constraints could be found.
+    fn next(&mut self) -> Option<Allocation> {
+        let attempts = cmp::min(self.attempts, MAX_ATTEMPTS);
+        for sha in self.shas.by_ref().take(attempts) {
+            self.attempts -= 1;
+            let phrase = sha
+                .parse::<Phrase>()
+                .expect("random shas are valid")
+                .with_case(self.constraints.case);
+            let name = phrase.to_string();
+            if self.constraints.allows(&name) && self.taken.insert(normalize_name(&name)) {
+                return Some(Allocation { sha, phrase });
+            }
+        }
+        self.out_of_attempts = attempts < MAX_ATTEMPTS;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_compared_loosely() {
+        let allocator = Allocator::new(["ambitiously-timeless-gemot"], Constraints::default(), 0);
+        assert!(allocator.is_taken("AmbitiouslyTimelessGemot"));
+        assert!(allocator.is_taken("ambitiously timeless gemot"));
+        assert!(!allocator.is_taken("ambitiously timeless rancor"));
+    }
+
+    #[test]
+    fn taken_names_are_skipped() {
+        let free: Vec<String> = Allocator::new(Vec::<String>::new(), Constraints::default(), 7)
+            .take(3)
+            .map(|a| a.phrase.to_string())
+            .collect();
+        let names: Vec<String> = Allocator::new(&free[..2], Constraints::default(), 7)
+            .take(1)
+            .map(|a| a.phrase.to_string())
+            .collect();
+        assert_eq!(names, &free[2..]);
+    }
+
+    #[test]
+    fn names_are_never_repeated() {
+        let names: HashSet<String> =
+            Allocator::new(Vec::<String>::new(), Constraints::default(), 1)
+                .take(500)
+                .map(|a| a.phrase.to_string())
+                .collect();
+        assert_eq!(names.len(), 500);
+    }
+
+    #[test]
+    fn constraints_are_applied() {
+        let constraints = Constraints {
+            case: Case::Kebab,
+            max_length: Some(20),
+            initial: Some('S'),
+        };
+        for allocation in Allocator::new(Vec::<String>::new(), constraints, 3).take(20) {
+            let name = allocation.phrase.to_string();
+            assert!(name.starts_with('s'), "{}", name);
+            assert!(name.len() <= 20, "{}", name);
+            assert!(name.contains('-'), "{}", name);
+        }
+    }
+
+    #[test]
+    fn impossible_constraints_give_up() {
+        let constraints = Constraints {
+            max_length: Some(3),
+            ..Constraints::default()
+        };
+        assert_eq!(
+            Allocator::new(Vec::<String>::new(), constraints, 0).next(),
+            None
+        );
+    }
+
+    #[test]
+    fn attempts_are_limited_across_names() {
+        let mut allocator =
+            Allocator::new(Vec::<String>::new(), Constraints::default(), 0).with_max_attempts(10);
+        assert_eq!(allocator.by_ref().take(20).count(), 10);
+        assert!(allocator.ran_out_of_attempts());
+
+        let constraints = Constraints {
+            max_length: Some(3),
+            ..Constraints::default()
+        };
+        let mut allocator = Allocator::new(Vec::<String>::new(), constraints, 0);
+        assert_eq!(allocator.next(), None);
+        assert!(!allocator.ran_out_of_attempts());
+    }
+}
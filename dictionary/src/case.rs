@@ -1,3 +1,4 @@
+use std::fmt::{Display, Error, Formatter};
 use std::str::FromStr;
 
 /// The various cases that can be supported. This is a type used to coerce
@@ -39,6 +40,22 @@ impl FromStr for Case {
     }
 }
 
+impl Display for Case {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let name = match *self {
+            Case::Snake => "snake",
+            Case::Kebab => "kebab",
+            Case::Pascal => "pascal",
+            Case::Camel => "camel",
+            Case::Title => "title",
+            Case::Sentence => "sentence",
+            Case::Lower => "lower",
+            Case::Upper => "upper",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,4 +72,13 @@ mod tests {
         assert_eq!(Case::Upper, "upper".parse::<Case>().unwrap());
         assert!("alsdkfj".parse::<Case>().is_err());
     }
+
+    #[test]
+    fn a_format_displays_as_its_name() {
+        for name in &[
+            "snake", "kebab", "pascal", "camel", "title", "sentence", "lower", "upper",
+        ] {
+            assert_eq!(name.parse::<Case>().unwrap().to_string(), *name);
+        }
+    }
 }
@@ -0,0 +1,240 @@
+use phrase::Phrase;
+use sha1::Sha1;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use DICTIONARY_VERSION;
+
+/// The algorithm used to turn arbitrary input into a sha that can be named.
+///
+/// Each dictionary version is pinned to exactly one algorithm so that the
+/// name for a given input never drifts between releases of this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HashAlgorithm {
+    /// SHA-1, the same digest git uses for object ids
+    Sha1,
+}
+
+impl HashAlgorithm {
+    /// The algorithm pinned to the given dictionary version, if the version
+    /// is known.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use git_release_name::HashAlgorithm;
+    ///
+    /// assert_eq!(HashAlgorithm::for_version(1), Some(HashAlgorithm::Sha1));
+    /// assert_eq!(HashAlgorithm::for_version(0), None);
+    /// ```
+    pub fn for_version(version: u32) -> Option<HashAlgorithm> {
+        match version {
+            1 => Some(HashAlgorithm::Sha1),
+            _ => None,
+        }
+    }
+
+    /// The algorithm pinned to the current dictionary version.
+    pub fn current() -> HashAlgorithm {
+        HashAlgorithm::for_version(DICTIONARY_VERSION).expect("current version has an algorithm")
+    }
+
+    /// Hashes the input and returns the hexadecimal digest.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use git_release_name::HashAlgorithm;
+    ///
+    /// assert_eq!(
+    ///     HashAlgorithm::Sha1.hex_digest(b"abc"),
+    ///     "a9993e364706816aba3e25717850c26c9cd0d89d"
+    /// );
+    /// ```
+    pub fn hex_digest(&self, input: &[u8]) -> String {
+        match *self {
+            HashAlgorithm::Sha1 => Sha1::from(input).digest().to_string(),
+        }
+    }
+}
+
+/// Looks up a phrase for any input, such as a branch name, a UUID or a build
+/// number, by hashing it with the algorithm pinned to the current dictionary
+/// version.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::lookup_hashed;
+///
+/// let phrase = lookup_hashed("feature/login");
+/// assert_eq!(phrase, lookup_hashed("feature/login"));
+/// ```
+pub fn lookup_hashed<B: AsRef<[u8]>>(input: B) -> Phrase {
+    HashAlgorithm::current()
+        .hex_digest(input.as_ref())
+        .parse()
+        .expect("digests are hexadecimal")
+}
+
+/// Computes the git blob id for some content. This is the same id that
+/// `git hash-object` prints for a file with these contents.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::blob_id;
+///
+/// assert_eq!(blob_id(b""), "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391");
+/// ```
+pub fn blob_id(content: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()).as_bytes());
+    hasher.update(content);
+    hasher.digest().to_string()
+}
+
+/// Looks up a phrase for some content by its git blob id, so a file is given
+/// the same name whether it is named here or by the id git stores it under.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::{lookup, name_for_bytes};
+///
+/// let phrase = name_for_bytes(b"hello world\n");
+/// assert_eq!(phrase, lookup("3b18e512dba79e4c8300dd08aeb37f8e728b8dad").unwrap());
+/// ```
+pub fn name_for_bytes(content: &[u8]) -> Phrase {
+    blob_id(content).parse().expect("digests are hexadecimal")
+}
+
+/// Computes the git blob id of `len` bytes of content read from the reader.
+/// The content is hashed as it is read so it is never held in memory, which
+/// is why its length has to be known up front for the blob header. Content
+/// that isn't exactly `len` bytes long is an error.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::{blob_id, blob_id_for_reader};
+///
+/// let content = b"hello world\n";
+/// let id = blob_id_for_reader(&content[..], content.len() as u64).unwrap();
+/// assert_eq!(id, blob_id(content));
+/// ```
+pub fn blob_id_for_reader<R: Read>(mut reader: R, len: u64) -> io::Result<String> {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", len).as_bytes());
+    let mut buf = [0; 64 * 1024];
+    let mut read = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        read += n as u64;
+        if read > len {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    if read != len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected {} bytes of content, read {}", len, read),
+        ));
+    }
+    Ok(hasher.digest().to_string())
+}
+
+/// Looks up a phrase for `len` bytes of content read from the reader by its
+/// git blob id, without holding the content in memory.
+pub fn name_for_reader<R: Read>(reader: R, len: u64) -> io::Result<Phrase> {
+    let id = blob_id_for_reader(reader, len)?;
+    Ok(id.parse().expect("digests are hexadecimal"))
+}
+
+/// Looks up a phrase for a file by its git blob id. The length comes from
+/// the file's metadata, so files of any size can be named as they are read.
+pub fn name_for_file<P: AsRef<Path>>(path: P) -> io::Result<Phrase> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    name_for_reader(file, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_current_version_uses_sha1() {
+        assert_eq!(HashAlgorithm::current(), HashAlgorithm::Sha1);
+    }
+
+    #[test]
+    fn hashed_names_are_stable() {
+        assert_eq!(
+            lookup_hashed("abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+                .parse::<Phrase>()
+                .unwrap()
+        );
+        assert_eq!(
+            lookup_hashed("1234").to_string(),
+            format!("{}", lookup_hashed(b"1234"))
+        );
+    }
+
+    #[test]
+    fn blob_ids_match_git() {
+        assert_eq!(
+            blob_id(b"hello world\n"),
+            "3b18e512dba79e4c8300dd08aeb37f8e728b8dad"
+        );
+    }
+
+    #[test]
+    fn readers_are_named_like_bytes() {
+        let content = b"some tarball contents";
+        assert_eq!(
+            name_for_reader(&content[..], content.len() as u64).unwrap(),
+            name_for_bytes(content)
+        );
+    }
+
+    #[test]
+    fn readers_must_have_the_given_length() {
+        let content = b"some tarball contents";
+        let len = content.len() as u64;
+        for &wrong in &[len - 1, len + 1] {
+            let err = name_for_reader(&content[..], wrong).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn files_are_named_like_their_contents() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let content = ::std::fs::read(path).unwrap();
+        assert_eq!(name_for_file(path).unwrap(), name_for_bytes(&content));
+    }
+
+    #[test]
+    fn non_hex_input_can_be_named() {
+        assert!("main".parse::<Phrase>().is_err());
+        // These are pinned to dictionary version 1 and must never change.
+        assert_eq!(
+            lookup_hashed("main").to_string(),
+            "deferentially earthen naphthene"
+        );
+        assert_eq!(
+            lookup_hashed("feature/login").to_string(),
+            "festally super keening"
+        );
+        assert_eq!(lookup_hashed("").to_string(), "inexactly mimic grampus");
+    }
+}
@@ -0,0 +1,227 @@
+use words;
+use Kind;
+
+/// The number of bits available in a sha once it has been truncated.
+const SHA_BITS: u32 = 32;
+
+/// Declares how the bits of a sha are split between the word slots.
+///
+/// Slots are listed from the most significant bits to the least significant.
+/// Every kind of word must appear exactly once and each slot may not address
+/// more indexes than its dictionary has words, so an index never has to wrap
+/// around the word list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitLayout {
+    slots: [(Kind, u32); 3],
+}
+
+/// Represents failures when declaring a layout.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[non_exhaustive]
+pub enum LayoutError {
+    /// A kind of word was not given a slot
+    MissingKind(Kind),
+    /// A kind of word was given more than one slot
+    DuplicateKind(Kind),
+    /// A slot was declared with zero bits
+    EmptySlot(Kind),
+    /// The slots use more bits than a sha provides
+    TooManyBits(u32),
+    /// A slot addresses more indexes than there are words
+    NotEnoughWords { kind: Kind, bits: u32, words: usize },
+}
+
+impl BitLayout {
+    /// Creates a new layout from a list of slots and validates it against
+    /// the built in dictionary.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use git_release_name::{BitLayout, Kind, LayoutError};
+    ///
+    /// let layout = BitLayout::new(&[(Kind::Noun, 12), (Kind::Adj, 8), (Kind::Adv, 12)]);
+    /// assert!(layout.is_ok());
+    ///
+    /// let layout = BitLayout::new(&[(Kind::Noun, 12), (Kind::Adj, 12), (Kind::Adv, 8)]);
+    /// assert_eq!(
+    ///     layout,
+    ///     Err(LayoutError::NotEnoughWords { kind: Kind::Adj, bits: 12, words: 256 })
+    /// );
+    /// ```
+    pub fn new(slots: &[(Kind, u32)]) -> Result<BitLayout, LayoutError> {
+        let layout = BitLayout::unchecked(slots)?;
+        for &(kind, _) in layout.slots() {
+            layout.validate(kind, words(kind).len())?;
+        }
+        Ok(layout)
+    }
+
+    /// Creates a new layout from a list of slots without checking it against
+    /// the built in dictionary. The structure of the layout is still
+    /// validated.
+    pub fn unchecked(slots: &[(Kind, u32)]) -> Result<BitLayout, LayoutError> {
+        for &kind in &[Kind::Adv, Kind::Adj, Kind::Noun] {
+            match slots.iter().filter(|&&(k, _)| k == kind).count() {
+                0 => return Err(LayoutError::MissingKind(kind)),
+                1 => {}
+                _ => return Err(LayoutError::DuplicateKind(kind)),
+            }
+        }
+        if let Some(&(kind, _)) = slots.iter().find(|&&(_, bits)| bits == 0) {
+            return Err(LayoutError::EmptySlot(kind));
+        }
+        let total = slots
+            .iter()
+            .try_fold(0u32, |total, &(_, bits)| total.checked_add(bits));
+        match total {
+            Some(total) if total <= SHA_BITS => {}
+            // A total too large to count is reported as the most there can be.
+            total => return Err(LayoutError::TooManyBits(total.unwrap_or(u32::MAX))),
+        }
+        Ok(BitLayout {
+            slots: [slots[0], slots[1], slots[2]],
+        })
+    }
+
+    /// Checks that the slot for `kind` can be used with a dictionary of
+    /// `words` words without wrapping.
+    pub fn validate(&self, kind: Kind, words: usize) -> Result<(), LayoutError> {
+        let bits = self.bits(kind);
+        if (1u64 << bits) > words as u64 {
+            Err(LayoutError::NotEnoughWords { kind, bits, words })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The slots from most to least significant bits.
+    pub fn slots(&self) -> &[(Kind, u32)] {
+        &self.slots
+    }
+
+    /// The number of bits used to pick a word of the given kind.
+    pub fn bits(&self, kind: Kind) -> u32 {
+        self.slots
+            .iter()
+            .find(|&&(k, _)| k == kind)
+            .map(|&(_, bits)| bits)
+            .expect("layouts have a slot for every kind")
+    }
+
+    /// Extracts the index of the word of the given kind from a sha value.
+    pub(crate) fn index(&self, value: u32, kind: Kind) -> usize {
+        let shift: u32 = self
+            .slots
+            .iter()
+            .skip_while(|&&(k, _)| k != kind)
+            .skip(1)
+            .map(|&(_, bits)| bits)
+            .sum();
+        let mask = (1u64 << self.bits(kind)) - 1;
+        ((u64::from(value) >> shift) & mask) as usize
+    }
+
+    /// Builds the sha value that has the given index for every kind, the
+    /// reverse of `index`.
+    pub(crate) fn compose(&self, index: impl Fn(Kind) -> usize) -> u32 {
+        self.slots.iter().fold(0u64, |value, &(kind, bits)| {
+            (value << bits) | index(kind) as u64
+        }) as u32
+    }
+}
+
+impl Default for BitLayout {
+    /// Adverbs use the top 12 bits, adjectives the next 8 and nouns the
+    /// bottom 12.
+    fn default() -> BitLayout {
+        BitLayout {
+            slots: [(Kind::Adv, 12), (Kind::Adj, 8), (Kind::Noun, 12)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_default_layout_is_valid() {
+        let layout = BitLayout::default();
+        assert_eq!(BitLayout::new(layout.slots()), Ok(layout));
+    }
+
+    #[test]
+    fn every_kind_needs_exactly_one_slot() {
+        assert_eq!(
+            BitLayout::new(&[(Kind::Adv, 12), (Kind::Noun, 12)]),
+            Err(LayoutError::MissingKind(Kind::Adj))
+        );
+        assert_eq!(
+            BitLayout::new(&[
+                (Kind::Adv, 4),
+                (Kind::Adj, 4),
+                (Kind::Noun, 4),
+                (Kind::Adv, 4)
+            ]),
+            Err(LayoutError::DuplicateKind(Kind::Adv))
+        );
+    }
+
+    #[test]
+    fn slots_must_fit_in_a_sha() {
+        assert_eq!(
+            BitLayout::unchecked(&[(Kind::Adv, 12), (Kind::Adj, 12), (Kind::Noun, 12)]),
+            Err(LayoutError::TooManyBits(36))
+        );
+        assert_eq!(
+            BitLayout::new(&[(Kind::Adv, 12), (Kind::Adj, 0), (Kind::Noun, 12)]),
+            Err(LayoutError::EmptySlot(Kind::Adj))
+        );
+    }
+
+    #[test]
+    fn slots_that_overflow_are_too_many_bits() {
+        assert_eq!(
+            BitLayout::unchecked(&[(Kind::Adv, u32::MAX), (Kind::Adj, 2), (Kind::Noun, 1)]),
+            Err(LayoutError::TooManyBits(u32::MAX))
+        );
+    }
+
+    #[test]
+    fn slots_are_validated_against_word_counts() {
+        let layout =
+            BitLayout::unchecked(&[(Kind::Adv, 10), (Kind::Adj, 12), (Kind::Noun, 10)]).unwrap();
+        assert_eq!(layout.validate(Kind::Adj, 4096), Ok(()));
+        assert_eq!(
+            layout.validate(Kind::Adj, 256),
+            Err(LayoutError::NotEnoughWords {
+                kind: Kind::Adj,
+                bits: 12,
+                words: 256
+            })
+        );
+    }
+
+    #[test]
+    fn indexes_follow_the_slot_order() {
+        let layout = BitLayout::new(&[(Kind::Noun, 12), (Kind::Adv, 12), (Kind::Adj, 8)]).unwrap();
+        assert_eq!(layout.index(0xabc12345, Kind::Noun), 0xabc);
+        assert_eq!(layout.index(0xabc12345, Kind::Adv), 0x123);
+        assert_eq!(layout.index(0xabc12345, Kind::Adj), 0x45);
+
+        let layout = BitLayout::new(&[(Kind::Adv, 4), (Kind::Adj, 4), (Kind::Noun, 4)]).unwrap();
+        assert_eq!(layout.index(0xabc, Kind::Adv), 0xa);
+        assert_eq!(layout.index(0xabc, Kind::Noun), 0xc);
+    }
+
+    #[test]
+    fn composing_reverses_indexes() {
+        let layout = BitLayout::new(&[(Kind::Noun, 12), (Kind::Adv, 12), (Kind::Adj, 8)]).unwrap();
+        let value = layout.compose(|kind| layout.index(0xabc12345, kind));
+        assert_eq!(value, 0xabc12345);
+
+        let layout = BitLayout::new(&[(Kind::Adv, 4), (Kind::Adj, 4), (Kind::Noun, 4)]).unwrap();
+        assert_eq!(layout.compose(|kind| layout.index(0xabc, kind)), 0xabc);
+    }
+}
@@ -1,14 +1,36 @@
 extern crate inflector;
+extern crate sha1;
 
 mod adjectives;
 mod adverbs;
+mod allocate;
 mod case;
+mod hash;
+mod layout;
 mod nouns;
 mod phrase;
+mod random;
+mod reverse;
 mod sha;
+pub mod stats;
 
+pub use self::allocate::{normalize_name, Allocation, Allocator, Constraints};
 pub use self::case::Case;
+pub use self::hash::{
+    blob_id, blob_id_for_reader, lookup_hashed, name_for_bytes, name_for_file, name_for_reader,
+    HashAlgorithm,
+};
+pub use self::layout::{BitLayout, LayoutError};
 pub use self::phrase::{ParsePhraseError, Phrase};
+pub use self::random::RandomShas;
+pub use self::reverse::reverse;
+
+/// The version of this crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The version of the dictionary and naming scheme. It changes whenever the
+/// same input could be given a different name.
+pub const DICTIONARY_VERSION: u32 = 1;
 
 /// Looks up a phrase from a given str slice. It should be able to look up
 /// any sized string but only if it's a valid hexadecimal.
@@ -35,15 +57,19 @@ pub struct Entry {
     pub index: usize,
 }
 
+/// The dictionary for a particular kind of word.
+fn words(kind: Kind) -> &'static [&'static str] {
+    match kind {
+        Kind::Noun => &nouns::WORDS,
+        Kind::Adv => &adverbs::WORDS,
+        Kind::Adj => &adjectives::WORDS,
+    }
+}
+
 /// Lists out the word for a particular kind of word.
 pub fn list(kind: Kind) -> Vec<Entry> {
-    let list = match kind {
-        Kind::Noun => &nouns::WORDS[..],
-        Kind::Adv => &adverbs::WORDS[..],
-        Kind::Adj => &adjectives::WORDS[..],
-    };
-
-    list.iter()
+    words(kind)
+        .iter()
         .map(|s| String::from(*s))
         .enumerate()
         .map(|(index, word)| Entry { kind, index, word })
@@ -67,7 +93,7 @@ mod tests {
 
     #[test]
     fn unique_function_detects_non_unique() {
-        assert!(!has_unique_elements(vec![1, 1].iter()));
+        assert!(!has_unique_elements([1, 1].iter()));
     }
 
     #[test]
@@ -1,10 +1,9 @@
-use adjectives;
-use adverbs;
 use case::Case;
-use nouns;
+use layout::{BitLayout, LayoutError};
 use sha::{ParseShaError, Sha};
 use std::fmt::{Display, Error, Formatter};
 use std::str::FromStr;
+use {words, Kind};
 
 /// A phrase that is made up of an adverb, adjective, noun.
 ///
@@ -15,10 +14,51 @@ pub struct Phrase {
     adj: String,
     adv: String,
     noun: String,
+    indexes: [usize; 3],
     format: Case,
 }
 
 impl Phrase {
+    /// Looks up a phrase using a custom bit layout instead of the default
+    /// one. The layout is checked against the dictionary before any words
+    /// are looked up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use git_release_name::{BitLayout, Kind, Phrase};
+    ///
+    /// let layout = BitLayout::new(&[(Kind::Noun, 12), (Kind::Adv, 12), (Kind::Adj, 8)]).unwrap();
+    /// let phrase = Phrase::parse_with_layout("00000123", &layout).unwrap();
+    /// assert_eq!(phrase.noun(), "kisses");
+    /// ```
+    pub fn parse_with_layout(sha: &str, layout: &BitLayout) -> Result<Phrase, ParsePhraseError> {
+        for &(kind, _) in layout.slots() {
+            layout
+                .validate(kind, words(kind).len())
+                .map_err(ParsePhraseError::Layout)?;
+        }
+
+        let sha = Sha::parse_prefix(sha).map_err(ParsePhraseError::Sha)?;
+
+        let indexes = [
+            sha.index(layout, Kind::Adv),
+            sha.index(layout, Kind::Adj),
+            sha.index(layout, Kind::Noun),
+        ];
+        let adv = lookup(indexes[0], words(Kind::Adv))?;
+        let adj = lookup(indexes[1], words(Kind::Adj))?;
+        let noun = lookup(indexes[2], words(Kind::Noun))?;
+
+        Ok(Phrase {
+            adv,
+            adj,
+            noun,
+            indexes,
+            format: Case::Lower,
+        })
+    }
+
     /// Consumes the current phrase and returns a new one with a different
     /// case.
     ///
@@ -81,6 +121,24 @@ impl Phrase {
         &self.noun
     }
 
+    /// The index in the dictionary of the word of a kind
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use git_release_name::{list, Kind, Phrase};
+    ///
+    /// let phrase: Phrase = "1234".parse().unwrap();
+    /// assert_eq!(list(Kind::Noun)[phrase.index(Kind::Noun)].word, phrase.noun());
+    /// ```
+    pub fn index(&self, kind: Kind) -> usize {
+        match kind {
+            Kind::Adv => self.indexes[0],
+            Kind::Adj => self.indexes[1],
+            Kind::Noun => self.indexes[2],
+        }
+    }
+
     /// The case the phrase will be formated with
     ///
     /// # Example
@@ -98,18 +156,23 @@ impl Phrase {
 }
 
 /// Represents failures during parsing.
+// The hidden variant predates `#[non_exhaustive]` and is kept so that matches
+// written against it still compile.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[allow(clippy::manual_non_exhaustive)]
 pub enum ParsePhraseError {
     /// The word was not found in the dictionary
     WordNotFound,
     Sha(ParseShaError),
+    /// The bit layout does not fit the dictionary
+    Layout(LayoutError),
     #[doc(hidden)]
     __NonExhaustive,
 }
 
 fn lookup(index: usize, words: &[&str]) -> Result<String, ParsePhraseError> {
     words
-        .get(index % words.len())
+        .get(index)
         .map(|s| s.to_string())
         .ok_or(ParsePhraseError::WordNotFound)
 }
@@ -118,19 +181,7 @@ impl FromStr for Phrase {
     type Err = ParsePhraseError;
 
     fn from_str(sha: &str) -> Result<Phrase, Self::Err> {
-        let sha = if sha.len() < 8 { &sha } else { &sha[..8] };
-        let sha: Sha = sha.parse().map_err(|e| ParsePhraseError::Sha(e))?;
-
-        let adv = lookup(sha.adverb(), &adverbs::WORDS)?;
-        let adj = lookup(sha.adjective(), &adjectives::WORDS)?;
-        let noun = lookup(sha.noun(), &nouns::WORDS)?;
-
-        Ok(Phrase {
-            adv,
-            adj,
-            noun,
-            format: Case::Lower,
-        })
+        Phrase::parse_with_layout(sha, &BitLayout::default())
     }
 }
 
@@ -180,6 +231,39 @@ mod tests {
         assert_eq!(overflow, underflow);
     }
 
+    #[test]
+    fn multi_byte_characters_are_not_a_sha() {
+        for sha in &[
+            "abcdefg\u{e9}",
+            "\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}",
+            "\u{1f600}abcdef",
+        ] {
+            assert!(sha.parse::<Phrase>().is_err());
+        }
+    }
+
+    #[test]
+    fn a_layout_changes_which_bits_pick_each_word() {
+        let layout = BitLayout::new(&[(Kind::Noun, 12), (Kind::Adj, 8), (Kind::Adv, 12)])
+            .expect("Invalid layout");
+        let phrase = Phrase::parse_with_layout("00a0a0a0", &layout).expect("Invalid phrase");
+        assert_eq!(phrase, make_simple_phrase());
+    }
+
+    #[test]
+    fn a_layout_that_does_not_fit_the_dictionary_is_rejected() {
+        let layout = BitLayout::unchecked(&[(Kind::Adv, 8), (Kind::Adj, 12), (Kind::Noun, 12)])
+            .expect("Invalid layout");
+        assert_eq!(
+            Phrase::parse_with_layout("abc", &layout),
+            Err(ParsePhraseError::Layout(LayoutError::NotEnoughWords {
+                kind: Kind::Adj,
+                bits: 12,
+                words: 256
+            }))
+        );
+    }
+
     #[test]
     fn a_phrase_can_be_formatted_as_snake_case() {
         let phrase = make_simple_phrase().with_case(Case::Snake);
@@ -0,0 +1,65 @@
+/// Generates a reproducible sequence of 8 character shas from a seed.
+///
+/// The sequence is produced with SplitMix64, as described by Steele, Lea
+/// and Flood in "Fast Splittable Pseudorandom Number Generators" (2014),
+/// and each sha is the top 32 bits of an output written as lower case hex.
+/// The algorithm only uses wrapping 64 bit integer arithmetic so the same
+/// seed yields the same shas on every platform. It is part of the public
+/// behaviour of this crate and won't change without a major version bump.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::RandomShas;
+///
+/// let shas: Vec<String> = RandomShas::new(0).take(2).collect();
+/// assert_eq!(shas, vec!["e220a839", "6e789e6a"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandomShas {
+    state: u64,
+}
+
+impl RandomShas {
+    pub fn new(seed: u64) -> RandomShas {
+        RandomShas { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl Iterator for RandomShas {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        Some(format!("{:08x}", self.next_u64() >> 32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_matches_the_reference_splitmix64() {
+        let mut rng = RandomShas::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_shas() {
+        let first: Vec<String> = RandomShas::new(42).take(5).collect();
+        let second: Vec<String> = RandomShas::new(42).take(5).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, RandomShas::new(43).take(5).collect::<Vec<_>>());
+        assert!(first.iter().all(|sha| sha.len() == 8));
+    }
+}
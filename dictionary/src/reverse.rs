@@ -0,0 +1,93 @@
+use allocate::normalize_name;
+use layout::BitLayout;
+use {words, Kind};
+
+/// The normalized words of a kind along with their indexes.
+fn normalized(kind: Kind) -> Vec<(usize, String)> {
+    words(kind)
+        .iter()
+        .map(|word| normalize_name(word))
+        .enumerate()
+        .collect()
+}
+
+/// Finds the shas that are given a name, using the default layout.
+///
+/// Names are compared ignoring case and separators, so a name in any case
+/// format can be reversed. Each sha is the 8 character prefix every sha
+/// starting with it shares. Rarely, words run together in more than one
+/// way and a name matches several shas, so all of them are returned.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::reverse;
+///
+/// assert_eq!(reverse("Intentionally-Mirky-Swineherds"), vec!["a9677113"]);
+/// assert!(reverse("not a release name").is_empty());
+/// ```
+pub fn reverse(name: &str) -> Vec<String> {
+    let layout = BitLayout::default();
+    let name = normalize_name(name);
+    let nouns = normalized(Kind::Noun);
+    let adjectives = normalized(Kind::Adj);
+
+    let mut shas = Vec::new();
+    for (adv, adverb) in normalized(Kind::Adv) {
+        let rest = match name.strip_prefix(adverb.as_str()) {
+            Some(rest) => rest,
+            None => continue,
+        };
+        for &(adj, ref adjective) in &adjectives {
+            let rest = match rest.strip_prefix(adjective.as_str()) {
+                Some(rest) => rest,
+                None => continue,
+            };
+            for &(noun, _) in nouns.iter().filter(|entry| entry.1 == rest) {
+                let value = layout.compose(|kind| match kind {
+                    Kind::Adv => adv,
+                    Kind::Adj => adj,
+                    Kind::Noun => noun,
+                });
+                shas.push(format!("{:08x}", value));
+            }
+        }
+    }
+    shas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {lookup, Case};
+
+    #[test]
+    fn names_reverse_to_their_sha() {
+        for sha in &["00000000", "ffffffff", "a9677113", "8d260e69"] {
+            let name = lookup(sha).unwrap().to_string();
+            assert!(reverse(&name).contains(&sha.to_string()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn any_case_can_be_reversed() {
+        let phrase = lookup("a9677113").unwrap();
+        for &case in &[
+            Case::Snake,
+            Case::Kebab,
+            Case::Pascal,
+            Case::Camel,
+            Case::Upper,
+        ] {
+            let name = phrase.clone().with_case(case).to_string();
+            assert_eq!(reverse(&name), vec!["a9677113"], "{}", name);
+        }
+    }
+
+    #[test]
+    fn unknown_names_have_no_sha() {
+        assert!(reverse("").is_empty());
+        assert!(reverse("intentionally mirky").is_empty());
+        assert!(reverse("intentionally mirky swineherdz").is_empty());
+    }
+}
@@ -1,4 +1,6 @@
+use layout::BitLayout;
 use std::str::FromStr;
+use Kind;
 
 /// Represents a sha. Provides convenience functions for library
 /// indexes.
@@ -6,7 +8,10 @@ use std::str::FromStr;
 pub struct Sha(u32);
 
 /// Error types for parsing a sha into a phrase/word.
+// The hidden variant predates `#[non_exhaustive]` and is kept so that matches
+// written against it still compile.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[allow(clippy::manual_non_exhaustive)]
 pub enum ParseShaError {
     /// The sha had non-hex characters in it
     NonHexadecimalCharacters,
@@ -18,7 +23,7 @@ impl FromStr for Sha {
     type Err = ParseShaError;
 
     fn from_str(sha: &str) -> Result<Sha, Self::Err> {
-        if let Ok(hash) = u32::from_str_radix(&sha, 16) {
+        if let Ok(hash) = u32::from_str_radix(sha, 16) {
             Ok(Sha(hash))
         } else {
             Err(ParseShaError::NonHexadecimalCharacters)
@@ -26,25 +31,17 @@ impl FromStr for Sha {
     }
 }
 
-const NIBBLES: u32 = 4;
-
 impl Sha {
-    /// Returns the adverb index for this sha
-    pub fn adverb(&self) -> usize {
-        const ADV_MASK: u32 = 0xfff00000;
-        ((self.0 & ADV_MASK) >> (5 * NIBBLES)) as usize
-    }
-
-    /// Returns the adjective index for this sha
-    pub fn adjective(&self) -> usize {
-        const ADJ_MASK: u32 = 0x000ff000;
-        ((self.0 & ADJ_MASK) >> (3 * NIBBLES)) as usize
+    /// Parses the first 8 characters of a sha, which are all a name is
+    /// formed from. A sha with a multi-byte character among its first 8
+    /// bytes can't be hexadecimal, so it is parsed whole and fails.
+    pub fn parse_prefix(sha: &str) -> Result<Sha, ParseShaError> {
+        sha.get(..8).unwrap_or(sha).parse()
     }
 
-    /// Returns the noun index for this sha
-    pub fn noun(&self) -> usize {
-        const NOUN_MASK: u32 = 0x00000fff;
-        (self.0 & NOUN_MASK) as usize
+    /// Returns the index of the word of the given kind for this sha
+    pub fn index(&self, layout: &BitLayout, kind: Kind) -> usize {
+        layout.index(self.0, kind)
     }
 }
 
@@ -52,6 +49,25 @@ impl Sha {
 mod tests {
     use super::*;
 
+    fn indexes(sha: Sha) -> (usize, usize, usize) {
+        let layout = BitLayout::default();
+        (
+            sha.index(&layout, Kind::Adv),
+            sha.index(&layout, Kind::Adj),
+            sha.index(&layout, Kind::Noun),
+        )
+    }
+
+    #[test]
+    fn only_the_first_eight_characters_are_parsed() {
+        assert_eq!(Sha::parse_prefix("abc"), "abc".parse());
+        assert_eq!(Sha::parse_prefix("0000abc123456789"), "0000abc1".parse());
+        assert_eq!(
+            Sha::parse_prefix("abcdefg\u{e9}"),
+            Err(ParseShaError::NonHexadecimalCharacters)
+        );
+    }
+
     #[test]
     fn it_can_parse_into_a_sha() {
         assert_eq!("a".parse::<Sha>().unwrap().0, 10);
@@ -64,24 +80,9 @@ mod tests {
 
     #[test]
     fn it_can_identify_indexes_for_each_type() {
-        let sha = Sha(0xffffffff);
-        assert_eq!(sha.adverb(), 4095);
-        assert_eq!(sha.adjective(), 255);
-        assert_eq!(sha.noun(), 4095);
-
-        let sha = Sha(0xfff00000);
-        assert_eq!(sha.adverb(), 4095);
-        assert_eq!(sha.adjective(), 0);
-        assert_eq!(sha.noun(), 0);
-
-        let sha = Sha(0x00ff000);
-        assert_eq!(sha.adverb(), 0);
-        assert_eq!(sha.adjective(), 255);
-        assert_eq!(sha.noun(), 0);
-
-        let sha = Sha(0x00000fff);
-        assert_eq!(sha.adverb(), 0);
-        assert_eq!(sha.adjective(), 0);
-        assert_eq!(sha.noun(), 4095);
+        assert_eq!(indexes(Sha(0xffffffff)), (4095, 255, 4095));
+        assert_eq!(indexes(Sha(0xfff00000)), (4095, 0, 0));
+        assert_eq!(indexes(Sha(0x00ff000)), (0, 255, 0));
+        assert_eq!(indexes(Sha(0x00000fff)), (0, 0, 4095));
     }
 }
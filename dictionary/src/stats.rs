@@ -0,0 +1,394 @@
+//! Statistics about how shas are distributed over the dictionary.
+//!
+//! Every name is built from three slots (adverb, adjective, noun). A
+//! `BitLayout` decides how many bits of the sha pick the word in each slot,
+//! and never gives a slot more indexes than its word list has words, so any
+//! words past the last index are simply never picked. The functions here
+//! describe how much information a layout keeps and how likely two inputs are
+//! to end up with the same name. Those ending in `_for` take the layout, and
+//! the others use the default one.
+
+use layout::BitLayout;
+use phrase::ParsePhraseError;
+use sha::Sha;
+use std::collections::HashSet;
+use {words, Kind};
+
+/// A single word position in a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    /// The kind of word that fills this slot.
+    pub kind: Kind,
+    /// How many bits of the sha are used to pick the word.
+    pub bits: u32,
+    /// How many words are available to pick from.
+    pub words: usize,
+}
+
+impl Slot {
+    /// The number of distinct indexes the sha bits can express.
+    pub fn indexes(&self) -> u64 {
+        1u64 << self.bits
+    }
+
+    /// The number of words that can actually be produced by this slot.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use git_release_name::stats;
+    ///
+    /// let slots = stats::slots();
+    /// assert_eq!(slots[1].reachable(), 256);
+    /// ```
+    pub fn reachable(&self) -> usize {
+        if (self.words as u64) < self.indexes() {
+            self.words
+        } else {
+            self.indexes() as usize
+        }
+    }
+
+    /// Whether every reachable word is equally likely to be picked. This is
+    /// only false for a slot with more indexes than words, which a
+    /// `BitLayout` never has, since the index would wrap around unevenly.
+    pub fn is_uniform(&self) -> bool {
+        self.indexes().is_multiple_of(self.words as u64) || (self.words as u64) > self.indexes()
+    }
+
+    /// The probability that a uniformly random sha picks the word at `index`.
+    pub fn probability(&self, index: usize) -> f64 {
+        if index >= self.reachable() {
+            return 0.0;
+        }
+        let words = self.words as u64;
+        let base = self.indexes() / words;
+        let extra = self.indexes() % words;
+        let hits = if (index as u64) < extra {
+            base + 1
+        } else {
+            base
+        };
+        hits as f64 / self.indexes() as f64
+    }
+
+    /// The Shannon entropy of this slot in bits.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use git_release_name::stats;
+    ///
+    /// let slots = stats::slots();
+    /// assert_eq!(slots[0].entropy(), 12.0);
+    /// ```
+    pub fn entropy(&self) -> f64 {
+        entropy((0..self.reachable()).map(|index| self.probability(index)))
+    }
+}
+
+/// The slots of the built in dictionary in the order they appear in a name.
+pub fn slots() -> Vec<Slot> {
+    slots_for(&BitLayout::default())
+}
+
+/// The slots of the built in dictionary split according to `layout`, in the
+/// order they appear in a name.
+pub fn slots_for(layout: &BitLayout) -> Vec<Slot> {
+    [Kind::Adv, Kind::Adj, Kind::Noun]
+        .iter()
+        .map(|&kind| Slot {
+            kind,
+            bits: layout.bits(kind),
+            words: words(kind).len(),
+        })
+        .collect()
+}
+
+/// The total entropy of a name in bits.
+pub fn total_entropy() -> f64 {
+    total_entropy_for(&BitLayout::default())
+}
+
+/// The total entropy of a name in bits when split according to `layout`.
+pub fn total_entropy_for(layout: &BitLayout) -> f64 {
+    slots_for(layout).iter().map(|slot| slot.entropy()).sum()
+}
+
+/// The number of distinct names that can be produced.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::stats;
+///
+/// assert_eq!(stats::name_space(), 1 << 32);
+/// ```
+pub fn name_space() -> u64 {
+    name_space_for(&BitLayout::default())
+}
+
+/// The number of distinct names that can be produced when split according
+/// to `layout`.
+pub fn name_space_for(layout: &BitLayout) -> u64 {
+    slots_for(layout)
+        .iter()
+        .map(|slot| slot.reachable() as u64)
+        .product()
+}
+
+/// The expected number of pairs that share a name among `items` uniformly
+/// random shas.
+pub fn expected_collisions(items: u64) -> f64 {
+    expected_collisions_for(&BitLayout::default(), items)
+}
+
+/// The expected number of pairs that share a name among `items` uniformly
+/// random shas when split according to `layout`.
+pub fn expected_collisions_for(layout: &BitLayout, items: u64) -> f64 {
+    pairs(items) / name_space_for(layout) as f64
+}
+
+/// The birthday bound probability that at least two of `items` uniformly
+/// random shas share a name.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::stats;
+///
+/// assert_eq!(stats::collision_probability(1), 0.0);
+/// assert!(stats::collision_probability(77_163) > 0.499);
+/// ```
+pub fn collision_probability(items: u64) -> f64 {
+    collision_probability_for(&BitLayout::default(), items)
+}
+
+/// The birthday bound probability that at least two of `items` uniformly
+/// random shas share a name when split according to `layout`.
+pub fn collision_probability_for(layout: &BitLayout, items: u64) -> f64 {
+    -(-expected_collisions_for(layout, items)).exp_m1()
+}
+
+fn pairs(items: u64) -> f64 {
+    (items as f64) * (items.saturating_sub(1) as f64) / 2.0
+}
+
+fn entropy<I>(probabilities: I) -> f64
+where
+    I: IntoIterator<Item = f64>,
+{
+    probabilities
+        .into_iter()
+        .filter(|&p| p > 0.0)
+        .map(|p| -p * p.log2())
+        .sum()
+}
+
+/// Observed word counts for one slot of a sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotSample {
+    /// The slot these counts were taken for.
+    pub slot: Slot,
+    /// How many times each word index was picked.
+    pub counts: Vec<u64>,
+}
+
+impl SlotSample {
+    fn new(slot: Slot) -> Self {
+        Self {
+            slot,
+            counts: vec![0; slot.words],
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The entropy of the observed distribution in bits.
+    pub fn entropy(&self) -> f64 {
+        let total = self.total() as f64;
+        entropy(self.counts.iter().map(|&count| count as f64 / total))
+    }
+
+    /// Pearson's chi-squared statistic against the expected distribution.
+    pub fn chi_squared(&self) -> f64 {
+        let total = self.total() as f64;
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(index, &count)| (count as f64, self.slot.probability(index) * total))
+            .filter(|&(_, expected)| expected > 0.0)
+            .map(|(observed, expected)| (observed - expected).powi(2) / expected)
+            .sum()
+    }
+
+    /// The degrees of freedom for the chi-squared statistic.
+    pub fn degrees_of_freedom(&self) -> usize {
+        self.slot.reachable().saturating_sub(1)
+    }
+
+    /// How many standard deviations the chi-squared statistic is from its
+    /// mean. Values far above 3 suggest the sample is not uniformly
+    /// distributed.
+    pub fn z_score(&self) -> f64 {
+        let df = self.degrees_of_freedom() as f64;
+        (self.chi_squared() - df) / (2.0 * df).sqrt()
+    }
+}
+
+/// The results of naming a sample of shas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// How many shas were in the sample.
+    pub count: u64,
+    /// How many distinct names were produced.
+    pub distinct: u64,
+    /// The per slot word counts in name order.
+    pub slots: Vec<SlotSample>,
+}
+
+impl Sample {
+    /// The number of shas that were given a name already in use.
+    pub fn collisions(&self) -> u64 {
+        self.count - self.distinct
+    }
+}
+
+/// Names every sha in `shas` and gathers the distribution of the words that
+/// were picked.
+///
+/// # Example
+///
+/// ```
+/// use git_release_name::stats;
+///
+/// let sample = stats::sample(["abc", "00000abc", "123"]).unwrap();
+/// assert_eq!(sample.count, 3);
+/// assert_eq!(sample.collisions(), 1);
+/// ```
+pub fn sample<I, S>(shas: I) -> Result<Sample, ParsePhraseError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    sample_for(&BitLayout::default(), shas)
+}
+
+/// Names every sha in `shas` using `layout` and gathers the distribution of
+/// the words that were picked.
+pub fn sample_for<I, S>(layout: &BitLayout, shas: I) -> Result<Sample, ParsePhraseError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut slots: Vec<SlotSample> = slots_for(layout).into_iter().map(SlotSample::new).collect();
+    let mut names = HashSet::new();
+    let mut count = 0;
+
+    for sha in shas {
+        let sha = Sha::parse_prefix(sha.as_ref()).map_err(ParsePhraseError::Sha)?;
+        let mut name = [0; 3];
+        for (i, slot) in slots.iter_mut().enumerate() {
+            let index = sha.index(layout, slot.slot.kind);
+            slot.counts[index] += 1;
+            name[i] = index;
+        }
+        names.insert(name);
+        count += 1;
+    }
+
+    Ok(Sample {
+        count,
+        distinct: names.len() as u64,
+        slots,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(bits: u32, words: usize) -> Slot {
+        Slot {
+            kind: Kind::Noun,
+            bits,
+            words,
+        }
+    }
+
+    #[test]
+    fn the_builtin_dictionary_uses_every_bit() {
+        assert_eq!(total_entropy(), 32.0);
+        assert!(slots().iter().all(|slot| slot.is_uniform()));
+    }
+
+    #[test]
+    fn slots_follow_the_layout() {
+        let layout = BitLayout::new(&[(Kind::Noun, 11), (Kind::Adj, 7), (Kind::Adv, 10)]).unwrap();
+        let slots = slots_for(&layout);
+        assert_eq!(slots[0].bits, 10);
+        assert_eq!(slots[1].reachable(), 128);
+        assert_eq!(slots.iter().map(|slot| slot.entropy()).sum::<f64>(), 28.0);
+        assert_eq!(total_entropy_for(&layout), 28.0);
+        assert_eq!(name_space_for(&layout), 1 << 28);
+        assert!(collision_probability_for(&layout, 77_163) > collision_probability(77_163));
+
+        let sample = sample_for(&layout, ["0ffe0000", "000003ff"]).unwrap();
+        assert_eq!(sample.slots[0].counts[1023], 1);
+        assert_eq!(sample.slots[2].counts[2047], 1);
+    }
+
+    #[test]
+    fn a_wrapping_slot_is_not_uniform() {
+        let slot = slot(2, 3);
+        assert!(!slot.is_uniform());
+        assert_eq!(slot.probability(0), 0.5);
+        assert_eq!(slot.probability(1), 0.25);
+        assert_eq!(slot.probability(2), 0.25);
+        assert_eq!(slot.entropy(), 1.5);
+    }
+
+    #[test]
+    fn a_slot_with_extra_words_cannot_reach_them() {
+        let slot = slot(2, 6);
+        assert!(slot.is_uniform());
+        assert_eq!(slot.reachable(), 4);
+        assert_eq!(slot.probability(3), 0.25);
+        assert_eq!(slot.probability(4), 0.0);
+        assert_eq!(slot.entropy(), 2.0);
+    }
+
+    #[test]
+    fn collisions_follow_the_birthday_bound() {
+        assert_eq!(collision_probability(0), 0.0);
+        assert!((collision_probability(77_163) - 0.5).abs() < 0.001);
+        assert_eq!(expected_collisions(2), 1.0 / (1u64 << 32) as f64);
+    }
+
+    #[test]
+    fn a_uniform_sample_scores_well() {
+        let shas = (0..4096u32).map(|i| format!("{:08x}", (i << 20) | ((i & 0xff) << 12) | i));
+        let sample = sample(shas).unwrap();
+        assert_eq!(sample.count, 4096);
+        assert_eq!(sample.collisions(), 0);
+        assert_eq!(sample.slots[2].entropy(), 12.0);
+        assert_eq!(sample.slots[2].chi_squared(), 0.0);
+    }
+
+    #[test]
+    fn a_skewed_sample_scores_poorly() {
+        let shas = (0..4096u32).map(|i| format!("{:08x}", i % 2));
+        let sample = sample(shas).unwrap();
+        assert_eq!(sample.distinct, 2);
+        assert_eq!(sample.slots[2].entropy(), 1.0);
+        assert!(sample.slots[2].z_score() > 3.0);
+    }
+
+    #[test]
+    fn invalid_shas_fail_the_sample() {
+        assert!(sample(["abc", "xyz"]).is_err());
+        assert!(sample(["abcdefg\u{e9}"]).is_err());
+    }
+}
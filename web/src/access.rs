@@ -0,0 +1,418 @@
+use actix_web::http::header::HeaderValue;
+use actix_web::middleware::{Finished, Middleware, Response, Started};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Result};
+use rand;
+use serde_json;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// The header a request id is read from and returned in.
+pub const REQUEST_ID: &str = "x-request-id";
+
+/// The longest request id that will be taken from a client.
+const MAX_REQUEST_ID: usize = 128;
+
+/// Routes polled by orchestrators. They are only logged at debug level so
+/// they don't drown out real traffic.
+const PROBES: &[&str] = &["healthz", "readyz", "metrics"];
+
+/// How access log lines are written.
+#[derive(Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Json,
+    Logfmt,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(format: &str) -> Result<LogFormat, ()> {
+        match format {
+            "json" => Ok(LogFormat::Json),
+            "logfmt" => Ok(LogFormat::Logfmt),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The least severe access log lines that are written. Successful requests
+/// are logged at info, client errors at warn and server errors at error.
+#[derive(Deserialize, Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Off,
+}
+
+impl FromStr for Level {
+    type Err = ();
+
+    fn from_str(level: &str) -> Result<Level, ()> {
+        match level {
+            "debug" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" => Ok(Level::Warn),
+            "error" => Ok(Level::Error),
+            "off" => Ok(Level::Off),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Off => "off",
+        }
+    }
+}
+
+struct Start(Instant);
+
+struct RequestId(String);
+
+struct Shas(usize);
+
+struct Names(String);
+
+/// Records how many shas a request named, for the access log.
+pub fn shas<S>(req: &mut HttpRequest<S>, count: usize) {
+    req.extensions_mut().insert(Shas(count));
+}
+
+/// Records the names a request handed out, for requests where seeing the
+/// names in the log is the point, such as webhooks.
+pub fn names<S>(req: &mut HttpRequest<S>, names: &[&str]) {
+    req.extensions_mut().insert(Names(names.join(",")));
+}
+
+/// One line of the access log.
+#[derive(Serialize, Debug, PartialEq)]
+struct Line<'a> {
+    ts: f64,
+    level: &'static str,
+    request_id: &'a str,
+    method: &'a str,
+    path: &'a str,
+    status: u16,
+    latency_ms: f64,
+    shas: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    names: Option<&'a str>,
+}
+
+impl<'a> Line<'a> {
+    fn json(&self) -> String {
+        serde_json::to_string(self).expect("log lines serialize")
+    }
+
+    fn logfmt(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "ts={:.3} level={} request_id={} method={} path={} status={} latency_ms={:.3} shas={}",
+            self.ts,
+            self.level,
+            logfmt_value(self.request_id),
+            logfmt_value(self.method),
+            logfmt_value(self.path),
+            self.status,
+            self.latency_ms,
+            self.shas
+        );
+        if let Some(names) = self.names {
+            let _ = write!(out, " names={}", logfmt_value(names));
+        }
+        out
+    }
+}
+
+/// A line about something a request started that finished after its
+/// response, such as forwarding a webhook.
+#[derive(Serialize, Debug, PartialEq)]
+struct Event<'a> {
+    ts: f64,
+    level: &'static str,
+    request_id: &'a str,
+    message: &'a str,
+}
+
+impl<'a> Event<'a> {
+    fn json(&self) -> String {
+        serde_json::to_string(self).expect("log lines serialize")
+    }
+
+    fn logfmt(&self) -> String {
+        format!(
+            "ts={:.3} level={} request_id={} message={}",
+            self.ts,
+            self.level,
+            logfmt_value(self.request_id),
+            logfmt_value(self.message)
+        )
+    }
+}
+
+fn logfmt_value(value: &str) -> String {
+    if value.is_empty()
+        || value.contains(|c: char| c == ' ' || c == '"' || c == '=' || c.is_control())
+    {
+        format!("{:?}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Takes the client's request id when it is reasonable, otherwise makes up
+/// a new one.
+fn request_id<S>(req: &HttpRequest<S>) -> String {
+    req.headers()
+        .get(REQUEST_ID)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+        })
+        .map(String::from)
+        .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()))
+}
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Writes lines to the access log on behalf of a request once it has been
+/// responded to, tagged with its request id.
+#[derive(Debug, Clone)]
+pub struct Logger {
+    log: AccessLog,
+    request_id: String,
+}
+
+impl Logger {
+    pub fn error(&self, message: &str) {
+        self.write(Level::Error, message);
+    }
+
+    fn write(&self, level: Level, message: &str) {
+        if level < self.log.level {
+            return;
+        }
+        let event = Event {
+            ts: now(),
+            level: level.as_str(),
+            request_id: &self.request_id,
+            message,
+        };
+        let line = match self.log.format {
+            LogFormat::Json => event.json(),
+            LogFormat::Logfmt => event.logfmt(),
+        };
+        let _ = writeln!(io::stdout().lock(), "{}", line);
+    }
+}
+
+/// A logger for the request, writing in the format and at the level of the
+/// app's access log. Requests that didn't go through the access log, such
+/// as in tests, log errors as logfmt.
+pub fn logger<S>(req: &HttpRequest<S>) -> Logger {
+    let log = req
+        .extensions()
+        .get::<AccessLog>()
+        .cloned()
+        .unwrap_or_else(|| AccessLog::new(LogFormat::Logfmt, Level::Error));
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .map_or_else(String::new, |RequestId(id)| id.clone());
+    Logger { log, request_id }
+}
+
+/// Writes a line for every request and tags it with a request id.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct AccessLog {
+    format: LogFormat,
+    level: Level,
+}
+
+impl AccessLog {
+    pub fn new(format: LogFormat, level: Level) -> Self {
+        AccessLog { format, level }
+    }
+
+    fn level<S>(req: &HttpRequest<S>, resp: &HttpResponse) -> Level {
+        if resp.status().is_server_error() {
+            Level::Error
+        } else if resp.status().is_client_error() {
+            Level::Warn
+        } else if PROBES.contains(&req.resource().name()) {
+            Level::Debug
+        } else {
+            Level::Info
+        }
+    }
+}
+
+impl<S> Middleware<S> for AccessLog {
+    fn start(&self, req: &mut HttpRequest<S>) -> Result<Started> {
+        let id = request_id(req);
+        req.extensions_mut().insert(Start(Instant::now()));
+        req.extensions_mut().insert(RequestId(id));
+        req.extensions_mut().insert(*self);
+        Ok(Started::Done)
+    }
+
+    fn response(&self, req: &mut HttpRequest<S>, mut resp: HttpResponse) -> Result<Response> {
+        if let Some(RequestId(id)) = req.extensions().get::<RequestId>() {
+            if let Ok(value) = HeaderValue::from_str(id) {
+                resp.headers_mut().insert(REQUEST_ID, value);
+            }
+        }
+        Ok(Response::Done(resp))
+    }
+
+    fn finish(&self, req: &mut HttpRequest<S>, resp: &HttpResponse) -> Finished {
+        let level = AccessLog::level(req, resp);
+        if level < self.level {
+            return Finished::Done;
+        }
+        let latency = req
+            .extensions()
+            .get::<Start>()
+            .map_or(0.0, |&Start(start)| start.elapsed().as_secs_f64());
+        let line = Line {
+            ts: now(),
+            level: level.as_str(),
+            request_id: req
+                .extensions()
+                .get::<RequestId>()
+                .map_or("", |RequestId(id)| id),
+            method: req.method().as_str(),
+            path: req.path(),
+            status: resp.status().as_u16(),
+            latency_ms: latency * 1000.0,
+            shas: req.extensions().get::<Shas>().map_or(0, |&Shas(n)| n),
+            names: req
+                .extensions()
+                .get::<Names>()
+                .map(|Names(names)| names.as_str()),
+        };
+        let line = match self.format {
+            LogFormat::Json => line.json(),
+            LogFormat::Logfmt => line.logfmt(),
+        };
+        // Failing to log must never fail the request.
+        let _ = writeln!(io::stdout().lock(), "{}", line);
+        Finished::Done
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn line<'a>(path: &'a str) -> Line<'a> {
+        Line {
+            ts: 1.5,
+            level: "info",
+            request_id: "abc",
+            method: "GET",
+            path,
+            status: 200,
+            latency_ms: 0.25,
+            shas: 2,
+            names: None,
+        }
+    }
+
+    #[test]
+    fn lines_can_be_logfmt() {
+        assert_eq!(
+            line("/api/release-name").logfmt(),
+            "ts=1.500 level=info request_id=abc method=GET path=/api/release-name status=200 \
+             latency_ms=0.250 shas=2"
+        );
+        assert!(line("/a b").logfmt().contains(" path=\"/a b\" "));
+
+        let named = Line {
+            names: Some("timeless gemot,tinkly rancor"),
+            ..line("/api/webhook")
+        };
+        assert!(named
+            .logfmt()
+            .ends_with(" shas=2 names=\"timeless gemot,tinkly rancor\""));
+    }
+
+    #[test]
+    fn lines_can_be_json() {
+        let json: serde_json::Value = serde_json::from_str(&line("/version").json()).unwrap();
+        assert_eq!(json["path"], "/version");
+        assert_eq!(json["request_id"], "abc");
+        assert_eq!(json["shas"], 2);
+        assert!(json.get("names").is_none());
+    }
+
+    #[test]
+    fn events_share_the_line_format() {
+        let event = Event {
+            ts: 1.5,
+            level: "error",
+            request_id: "abc",
+            message: "Unable to forward webhook",
+        };
+        assert_eq!(
+            event.logfmt(),
+            "ts=1.500 level=error request_id=abc message=\"Unable to forward webhook\""
+        );
+        let json: serde_json::Value = serde_json::from_str(&event.json()).unwrap();
+        assert_eq!(json["level"], "error");
+        assert_eq!(json["message"], "Unable to forward webhook");
+    }
+
+    #[test]
+    fn loggers_follow_the_access_log() {
+        let mut req = TestRequest::with_header("X-Request-Id", "trace-1234").finish();
+        let log = AccessLog::new(LogFormat::Json, Level::Off);
+        log.start(&mut req).unwrap();
+        let logger = logger(&req);
+        assert_eq!(logger.log, log);
+        assert_eq!(logger.request_id, "trace-1234");
+
+        let logger = self::logger(&TestRequest::default().finish());
+        assert_eq!(logger.log, AccessLog::new(LogFormat::Logfmt, Level::Error));
+    }
+
+    #[test]
+    fn request_ids_are_propagated_when_sane() {
+        let req = TestRequest::with_header("X-Request-Id", "trace-1234").finish();
+        assert_eq!(request_id(&req), "trace-1234");
+
+        let req = TestRequest::with_header("X-Request-Id", "no spaces please").finish();
+        assert_eq!(request_id(&req).len(), 16);
+        assert_ne!(request_id(&TestRequest::default().finish()), "");
+    }
+
+    #[test]
+    fn levels_parse_and_order() {
+        assert_eq!("warn".parse(), Ok(Level::Warn));
+        assert_eq!("logfmt".parse(), Ok(LogFormat::Logfmt));
+        assert!("verbose".parse::<Level>().is_err());
+        assert!(Level::Debug < Level::Info && Level::Error < Level::Off);
+    }
+}
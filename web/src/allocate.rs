@@ -0,0 +1,201 @@
+use super::{Name, Response};
+use access;
+use actix_web::http::header::HeaderValue;
+use actix_web::{HttpRequest, HttpResponse, Json};
+use error::ApiError;
+use git_release_name::{Allocator, Constraints};
+use openapi::{self, Parameters, Schema};
+use output::{Output, Row};
+use param::{self, Format, Query};
+use rand;
+use random::{MAX_COUNT, SEED};
+use serde_json::Value;
+
+/// How many shas one request may try in total, however many names it asks
+/// for, so that tight constraints can't keep a worker busy for long.
+const MAX_ATTEMPTS: usize = 100_000;
+
+#[derive(Deserialize)]
+pub struct Params {
+    output: Option<String>,
+}
+
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![openapi::output()]
+    }
+}
+
+/// The names already in use and the constraints on the new names.
+#[derive(Deserialize)]
+pub struct Body {
+    #[serde(default)]
+    taken: Vec<String>,
+    count: Option<usize>,
+    #[serde(flatten)]
+    options: Options,
+}
+
+impl Schema for Body {
+    fn schema() -> Value {
+        let mut properties = vec![
+            ("taken", openapi::array(openapi::string())),
+            (
+                "count",
+                json!({ "type": "integer", "minimum": 0, "maximum": MAX_COUNT }),
+            ),
+        ];
+        properties.extend(Options::properties());
+        let optional: Vec<&str> = properties.iter().map(|&(name, _)| name).collect();
+        openapi::object(&properties, &optional)
+    }
+}
+
+/// How new names are generated, shared by everything that hands out names.
+#[derive(Deserialize, Default)]
+pub struct Options {
+    pub seed: Option<u64>,
+    format: Option<String>,
+    version: Option<u32>,
+    max_length: Option<usize>,
+    initial: Option<String>,
+}
+
+impl Options {
+    fn initial(&self) -> Result<Option<char>, ApiError> {
+        match self.initial {
+            None => Ok(None),
+            Some(ref initial) => {
+                let mut chars = initial.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(Some(c)),
+                    _ => Err(ApiError::invalid_body("initial must be a single letter")),
+                }
+            }
+        }
+    }
+
+    /// Describes the options, which are all optional, for the bodies they
+    /// are part of.
+    pub fn properties() -> Vec<(&'static str, Value)> {
+        vec![
+            ("seed", openapi::integer()),
+            ("format", Format::schema()),
+            ("version", openapi::integer()),
+            ("max_length", openapi::integer()),
+            (
+                "initial",
+                json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+            ),
+        ]
+    }
+
+    /// Checks the options and turns them into constraints for an
+    /// `Allocator`.
+    pub fn constraints(&self) -> Result<Constraints, ApiError> {
+        let format = Format::from_param(self.format.as_deref())?;
+        param::version(self.version.map(|v| v.to_string()).as_deref())?;
+        Ok(Constraints {
+            case: *format,
+            max_length: self.max_length,
+            initial: self.initial()?,
+        })
+    }
+}
+
+/// Allocates `count` free names, failing if the constraints rule out
+/// enough of them or finding them takes more than `MAX_ATTEMPTS` tries.
+fn names(body: &Body, constraints: Constraints, seed: u64) -> Result<Vec<Name>, ApiError> {
+    let count = body.count.unwrap_or(1);
+    let mut allocator =
+        Allocator::new(&body.taken, constraints, seed).with_max_attempts(MAX_ATTEMPTS);
+    let names: Vec<Name> = allocator
+        .by_ref()
+        .take(count)
+        .map(|allocation| Name::new(allocation.phrase.to_string(), allocation.sha))
+        .collect();
+    if names.len() < count {
+        if allocator.ran_out_of_attempts() {
+            return Err(ApiError::too_many_attempts(MAX_ATTEMPTS));
+        }
+        return Err(ApiError::no_free_names(count));
+    }
+    Ok(names)
+}
+
+pub fn handler(
+    mut req: HttpRequest,
+    q: Query<Params>,
+    body: Json<Body>,
+) -> Result<HttpResponse, ApiError> {
+    let constraints = body.options.constraints()?;
+    let output = Output::negotiate(&req, q.output.as_deref(), Output::Json)?;
+    let count = body.count.unwrap_or(1);
+    if count > MAX_COUNT {
+        return Err(ApiError::too_many_shas(count, MAX_COUNT));
+    }
+    access::shas(&mut req, count);
+
+    let seed = body.options.seed.unwrap_or_else(rand::random);
+    let names = names(&body, constraints, seed)?;
+    let rows: Vec<Row> = names
+        .iter()
+        .map(|name| Row::new(name.sha(), Some(name.name())))
+        .collect();
+    let mut resp = output.respond(&Response::new(&names), &rows);
+    resp.headers_mut().insert(
+        SEED,
+        HeaderValue::from_str(&seed.to_string()).expect("seeds are numeric"),
+    );
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json;
+
+    fn body(json: &str) -> Body {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn taken_names_are_avoided() {
+        let body = body(r#"{"taken": ["forcibly endways holdback"], "count": 2}"#);
+        let names = serde_json::to_value(names(&body, Constraints::default(), 0).unwrap());
+        assert_eq!(names.unwrap()[0]["name"], "unpeacefully costly protest");
+    }
+
+    #[test]
+    fn exhausted_constraints_are_an_error() {
+        let body = body(r#"{"max_length": 3}"#);
+        let constraints = Constraints {
+            max_length: Some(3),
+            ..Constraints::default()
+        };
+        assert_eq!(
+            names(&body, constraints, 0).err(),
+            Some(ApiError::no_free_names(1))
+        );
+    }
+
+    #[test]
+    fn attempts_are_limited_per_request() {
+        let body = body(r#"{"count": 1000, "initial": "q"}"#);
+        let constraints = body.options.constraints().unwrap();
+        assert_eq!(
+            names(&body, constraints, 0).err(),
+            Some(ApiError::too_many_attempts(MAX_ATTEMPTS))
+        );
+        assert_eq!(
+            ApiError::too_many_attempts(MAX_ATTEMPTS).status().as_u16(),
+            422
+        );
+    }
+
+    #[test]
+    fn initials_are_single_letters() {
+        assert_eq!(body(r#"{"initial": "q"}"#).options.initial(), Ok(Some('q')));
+        assert!(body(r#"{"initial": "qu"}"#).options.initial().is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::{Middleware, Started};
+use actix_web::{HttpMessage, HttpRequest, Result};
+use error::ApiError;
+use serde::de::{self, Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use signature;
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use v2;
+
+/// Routes that are served without a key when keys are required. Probes have
+/// to work without one, and webhooks and slash commands are signed instead.
+pub const PUBLIC_ROUTES: &[&str] = &[
+    "healthz", "readyz", "metrics", "openapi", "webhook", "slash",
+];
+
+/// The header API keys can be sent in, besides `Authorization: Bearer`.
+const API_KEY: &str = "x-api-key";
+
+/// The header proxies name the addresses a request was forwarded for in.
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Who made a request: the name of the API key they sent, or the address
+/// they connected from.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Client {
+    Key(String),
+    Address(String),
+}
+
+impl Client {
+    /// The client of a request, once it has been authenticated.
+    pub fn of<S>(req: &HttpRequest<S>) -> Client {
+        req.extensions()
+            .get::<Client>()
+            .cloned()
+            .unwrap_or_else(|| address(req, &[]))
+    }
+}
+
+/// A proxy whose `X-Forwarded-For` header is trusted: an address, or `unix`
+/// for everything connecting over the unix socket.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Proxy {
+    Unix,
+    Ip(IpAddr),
+}
+
+impl FromStr for Proxy {
+    type Err = ();
+
+    fn from_str(proxy: &str) -> Result<Proxy, ()> {
+        match proxy.trim() {
+            "unix" => Ok(Proxy::Unix),
+            ip => ip.parse().map(Proxy::Ip).map_err(|_| ()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Proxy {
+    fn deserialize<D>(d: D) -> Result<Proxy, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(d)?;
+        s.parse()
+            .map_err(|_| de::Error::custom("expected an ip address or unix"))
+    }
+}
+
+/// The address a request came from. Connections from trusted proxies are
+/// followed back through `X-Forwarded-For`, from the right, to the first
+/// address that isn't a trusted proxy, since only the entries the proxies
+/// added themselves can be believed.
+fn address<S>(req: &HttpRequest<S>, proxies: &[Proxy]) -> Client {
+    let trusted = |hop: Option<IpAddr>| proxies.contains(&hop.map_or(Proxy::Unix, Proxy::Ip));
+    let mut hop = req.peer_addr().map(|addr| addr.ip());
+    if trusted(hop) {
+        let forwarded: Vec<&str> = req
+            .headers()
+            .get_all(FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+        for addr in forwarded.into_iter().rev() {
+            match addr.parse() {
+                Ok(ip) if trusted(hop) => hop = Some(ip),
+                _ => break,
+            }
+        }
+    }
+    Client::Address(hop.map_or_else(|| String::from("unknown"), |ip| ip.to_string()))
+}
+
+/// API keys by the SHA-256 of the key, so the keys themselves are never
+/// stored. Each key has a name to tell clients apart.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Keys(HashMap<Vec<u8>, String>);
+
+impl Keys {
+    /// Loads keys from a file, with one `name:sha256` per line, and from
+    /// entries in the same form. Blank lines and lines starting with `#`
+    /// are skipped.
+    pub fn load(file: Option<&Path>, entries: &[String]) -> Result<Keys, String> {
+        let mut keys = Keys::default();
+        if let Some(path) = file {
+            let contents =
+                fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            for line in contents.lines() {
+                keys.add(line)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+            }
+        }
+        for entry in entries {
+            keys.add(entry)?;
+        }
+        Ok(keys)
+    }
+
+    fn add(&mut self, entry: &str) -> Result<(), String> {
+        let entry = entry.trim();
+        if entry.is_empty() || entry.starts_with('#') {
+            return Ok(());
+        }
+        let (name, hash) = entry
+            .rsplit_once(':')
+            .ok_or_else(|| String::from("keys must be given as name:sha256"))?;
+        match signature::hex(hash) {
+            Some(ref digest) if digest.len() == 32 && !name.is_empty() => {
+                self.0.insert(digest.clone(), name.to_string());
+                Ok(())
+            }
+            _ => Err(format!("invalid key for {:?}, expected name:sha256", name)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The name of a key, if it is one of the keys.
+    fn name(&self, key: &str) -> Option<&str> {
+        self.0
+            .get(Sha256::digest(key.as_bytes()).as_slice())
+            .map(String::as_str)
+    }
+}
+
+/// Authenticates requests by their API key. Keys are optional unless they
+/// are required, but a key that is sent must be valid. The client is left
+/// in the request for rate limiting.
+#[derive(Debug, Default, Clone)]
+pub struct Auth {
+    keys: Arc<Keys>,
+    required: bool,
+    public: Arc<Vec<String>>,
+    proxies: Arc<Vec<Proxy>>,
+}
+
+impl Auth {
+    pub fn new(keys: Keys, required: bool, public: Vec<String>, proxies: Vec<Proxy>) -> Auth {
+        Auth {
+            keys: Arc::new(keys),
+            required,
+            public: Arc::new(public),
+            proxies: Arc::new(proxies),
+        }
+    }
+
+    fn client<S>(&self, req: &HttpRequest<S>) -> Result<Client, ApiError> {
+        let route = req.resource().name();
+        if self.public.iter().any(|public| public == route) {
+            return Ok(address(req, &self.proxies));
+        }
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let key = header(API_KEY).or_else(|| {
+            header(header::AUTHORIZATION.as_str()).and_then(|auth| auth.strip_prefix("Bearer "))
+        });
+        match key {
+            Some(key) => self
+                .keys
+                .name(key.trim())
+                .map(|name| Client::Key(name.to_string()))
+                .ok_or_else(ApiError::invalid_api_key),
+            None if self.required => Err(ApiError::missing_api_key()),
+            None => Ok(address(req, &self.proxies)),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Auth {
+    fn start(&self, req: &mut HttpRequest<S>) -> Result<Started> {
+        match self.client(req) {
+            Ok(client) => {
+                req.extensions_mut().insert(client);
+                Ok(Started::Done)
+            }
+            Err(err) => {
+                let mut resp = v2::error_response(req, &err);
+                resp.headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+                Ok(Started::Response(resp))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    /// The SHA-256 of `hunter2`.
+    const HUNTER2: &str = "f52fbd32b2b3b86ff88ef6c490628285f482af15ddcb29541f94bcf526a3f6c7";
+
+    fn keys() -> Keys {
+        Keys::load(None, &[format!("ci:{}", HUNTER2)]).unwrap()
+    }
+
+    fn auth(required: bool) -> Auth {
+        Auth::new(keys(), required, vec![String::from("healthz")], Vec::new())
+    }
+
+    #[test]
+    fn keys_are_looked_up_by_their_hash() {
+        assert_eq!(keys().name("hunter2"), Some("ci"));
+        assert_eq!(keys().name("hunter3"), None);
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected() {
+        assert!(Keys::load(None, &[String::from("ci")]).is_err());
+        assert!(Keys::load(None, &[String::from("ci:abc")]).is_err());
+        assert!(Keys::load(None, &[format!(":{}", HUNTER2)]).is_err());
+        let keys = Keys::load(None, &[String::from("# comment"), String::new()]).unwrap();
+        assert!(keys.is_empty());
+    }
+
+    #[test]
+    fn keys_can_be_sent_in_either_header() {
+        let auth = auth(false);
+        let req = TestRequest::with_header("X-Api-Key", "hunter2").finish();
+        assert_eq!(auth.client(&req), Ok(Client::Key(String::from("ci"))));
+        let req = TestRequest::with_header("Authorization", "Bearer hunter2").finish();
+        assert_eq!(auth.client(&req), Ok(Client::Key(String::from("ci"))));
+        let req = TestRequest::with_header("Authorization", "Bearer hunter3").finish();
+        assert_eq!(auth.client(&req), Err(ApiError::invalid_api_key()));
+    }
+
+    #[test]
+    fn keys_are_optional_unless_required() {
+        let req = TestRequest::default().finish();
+        assert_eq!(
+            auth(false).client(&req),
+            Ok(Client::Address(String::from("unknown")))
+        );
+        assert_eq!(auth(true).client(&req), Err(ApiError::missing_api_key()));
+    }
+
+    #[test]
+    fn only_trusted_proxies_name_the_client() {
+        let address = |proxies: &[Proxy], forwarded: &str| {
+            let req = TestRequest::with_header("X-Forwarded-For", forwarded).finish();
+            match super::address(&req, proxies) {
+                Client::Address(addr) => addr,
+                other => panic!("unexpected {:?}", other),
+            }
+        };
+        let proxy = Proxy::Ip("10.0.0.1".parse().unwrap());
+        // Test requests have no peer address, as on the unix socket.
+        assert_eq!(address(&[], "192.0.2.7"), "unknown");
+        assert_eq!(address(&[Proxy::Unix], "192.0.2.7"), "192.0.2.7");
+        // Entries left of the first untrusted address were sent by the client.
+        assert_eq!(
+            address(&[Proxy::Unix, proxy], "203.0.113.9, 192.0.2.7, 10.0.0.1"),
+            "192.0.2.7"
+        );
+        assert_eq!(address(&[Proxy::Unix, proxy], "10.0.0.1"), "10.0.0.1");
+        assert_eq!(address(&[Proxy::Unix], "bogus, 192.0.2.7"), "192.0.2.7");
+        assert_eq!(address(&[Proxy::Unix], "bogus"), "unknown");
+    }
+}
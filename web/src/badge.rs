@@ -0,0 +1,359 @@
+use access;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, Path};
+use cache::Etag;
+use error::ApiError;
+use metrics;
+use openapi::{self, Parameters};
+use output::escape;
+use param::{self, Format, Query};
+use serde_json::Value;
+
+/// Height of flat badges in pixels.
+const HEIGHT: u32 = 20;
+
+/// Horizontal padding on each side of the text in pixels.
+const PADDING: u32 = 5;
+
+#[derive(Deserialize)]
+pub struct Params {
+    label: Option<String>,
+    color: Option<String>,
+    label_color: Option<String>,
+    style: Option<String>,
+    format: Option<String>,
+    version: Option<String>,
+}
+
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        let color = "A shields.io colour name or a hex colour";
+        vec![
+            openapi::query("label", "The text on the left", openapi::string()),
+            openapi::query("color", color, openapi::string()),
+            openapi::query("label_color", color, openapi::string()),
+            openapi::query(
+                "style",
+                "The look of the badge",
+                openapi::string_enum(&["flat", "flat-square", "plastic"]),
+            ),
+            openapi::format(),
+            openapi::version(),
+        ]
+    }
+}
+
+/// The look of the badge, following shields.io.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Style {
+    Flat,
+    FlatSquare,
+    Plastic,
+}
+
+impl Style {
+    fn from_param(style: Option<&str>) -> Result<Style, ApiError> {
+        match style {
+            None | Some("flat") => Ok(Style::Flat),
+            Some("flat-square") => Ok(Style::FlatSquare),
+            Some("plastic") => Ok(Style::Plastic),
+            Some(style) => Err(ApiError::invalid_query(&format!(
+                "Unknown badge style, expected flat, flat-square or plastic: {}",
+                style
+            ))),
+        }
+    }
+}
+
+/// Resolves a named shields.io colour or a 3 or 6 digit hex colour, with or
+/// without the leading `#`.
+fn color(color: &str) -> Result<String, ApiError> {
+    let named = match color {
+        "brightgreen" => Some("#4c1"),
+        "green" => Some("#97ca00"),
+        "yellowgreen" => Some("#a4a61d"),
+        "yellow" => Some("#dfb317"),
+        "orange" => Some("#fe7d37"),
+        "red" => Some("#e05d44"),
+        "blue" => Some("#007ec6"),
+        "grey" | "gray" => Some("#555"),
+        "lightgrey" | "lightgray" => Some("#9f9f9f"),
+        _ => None,
+    };
+    if let Some(named) = named {
+        return Ok(named.to_string());
+    }
+    let hex = color.trim_start_matches('#');
+    if (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(format!("#{}", hex.to_ascii_lowercase()))
+    } else {
+        Err(ApiError::invalid_query(&format!(
+            "Unknown badge color: {}",
+            color
+        )))
+    }
+}
+
+/// Estimates the width of text rendered in 11px Verdana, the font badges
+/// are drawn with. Characters outside of ascii are given the width of a
+/// wide letter.
+fn text_width(text: &str) -> f64 {
+    text.chars()
+        .map(|c| match c {
+            ' ' => 3.87,
+            '!' => 4.33,
+            '"' => 5.05,
+            '\'' => 2.95,
+            ',' | '.' => 4.0,
+            '(' | ')' | '-' | '/' | ':' | ';' | '[' | '\\' | ']' | '|' => 4.99,
+            '?' => 6.0,
+            '0'..='9' | '$' | '*' | '_' | '`' => 7.0,
+            '#' | '+' | '<' | '=' | '>' | '^' | '~' => 9.0,
+            '{' | '}' => 6.98,
+            '&' => 7.99,
+            '@' => 11.0,
+            '%' => 11.84,
+            'A' | 'S' | 'V' => 7.52,
+            'B' | 'X' | 'Z' => 7.54,
+            'C' => 7.68,
+            'D' => 8.48,
+            'E' => 6.96,
+            'F' => 6.32,
+            'G' => 8.53,
+            'H' => 8.27,
+            'I' => 4.61,
+            'J' => 5.0,
+            'K' => 7.62,
+            'L' => 6.12,
+            'M' => 9.27,
+            'N' => 8.23,
+            'O' | 'Q' => 8.66,
+            'P' => 6.63,
+            'R' => 7.65,
+            'T' => 6.78,
+            'U' => 8.05,
+            'W' => 10.88,
+            'Y' => 6.77,
+            'a' => 6.61,
+            'b' | 'd' | 'g' | 'p' | 'q' => 6.85,
+            'c' | 's' => 5.73,
+            'e' => 6.55,
+            'f' => 3.87,
+            'h' | 'n' | 'u' => 6.96,
+            'i' | 'l' => 3.02,
+            'j' => 3.79,
+            'k' | 'v' | 'x' | 'y' => 6.51,
+            'm' => 10.75,
+            'o' => 6.68,
+            'r' => 4.69,
+            't' => 4.33,
+            'w' => 8.98,
+            'z' => 5.78,
+            _ => 9.0,
+        })
+        .sum()
+}
+
+/// A two part badge with a label on the left and a message on the right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Badge<'a> {
+    label: &'a str,
+    message: &'a str,
+    color: &'a str,
+    label_color: &'a str,
+    style: Style,
+}
+
+impl<'a> Badge<'a> {
+    fn section_width(text: &str) -> u32 {
+        text_width(text).ceil() as u32 + 2 * PADDING
+    }
+
+    pub fn render(&self) -> String {
+        let label_width = Badge::section_width(self.label);
+        let message_width = Badge::section_width(self.message);
+        let width = label_width + message_width;
+        let (height, radius, gradient) = match self.style {
+            Style::Flat => (
+                HEIGHT,
+                3,
+                "<linearGradient id=\"s\" x2=\"0\" y2=\"100%\">\
+                 <stop offset=\"0\" stop-color=\"#bbb\" stop-opacity=\".1\"/>\
+                 <stop offset=\"1\" stop-opacity=\".1\"/></linearGradient>",
+            ),
+            Style::FlatSquare => (HEIGHT, 0, ""),
+            Style::Plastic => (
+                18,
+                4,
+                "<linearGradient id=\"s\" x2=\"0\" y2=\"100%\">\
+                 <stop offset=\"0\" stop-color=\"#fff\" stop-opacity=\".7\"/>\
+                 <stop offset=\".1\" stop-color=\"#aaa\" stop-opacity=\".1\"/>\
+                 <stop offset=\".9\" stop-opacity=\".3\"/>\
+                 <stop offset=\"1\" stop-opacity=\".5\"/></linearGradient>",
+            ),
+        };
+        let overlay = if gradient.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "<rect width=\"{}\" height=\"{}\" fill=\"url(#s)\"/>",
+                width, height
+            )
+        };
+        let label = escape(self.label);
+        let message = escape(self.message);
+        let text_y = height / 2 + 4;
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             role=\"img\" aria-label=\"{label}: {message}\">\
+             <title>{label}: {message}</title>{gradient}\
+             <clipPath id=\"r\"><rect width=\"{width}\" height=\"{height}\" rx=\"{radius}\" fill=\"#fff\"/></clipPath>\
+             <g clip-path=\"url(#r)\">\
+             <rect width=\"{label_width}\" height=\"{height}\" fill=\"{label_color}\"/>\
+             <rect x=\"{label_width}\" width=\"{message_width}\" height=\"{height}\" fill=\"{color}\"/>\
+             {overlay}</g>\
+             <g fill=\"#fff\" text-anchor=\"middle\" \
+             font-family=\"Verdana,Geneva,DejaVu Sans,sans-serif\" font-size=\"11\">\
+             <text x=\"{label_x}\" y=\"{shadow_y}\" fill=\"#010101\" fill-opacity=\".3\">{label}</text>\
+             <text x=\"{label_x}\" y=\"{text_y}\">{label}</text>\
+             <text x=\"{message_x}\" y=\"{shadow_y}\" fill=\"#010101\" fill-opacity=\".3\">{message}</text>\
+             <text x=\"{message_x}\" y=\"{text_y}\">{message}</text>\
+             </g></svg>",
+            width = width,
+            height = height,
+            radius = radius,
+            gradient = gradient,
+            overlay = overlay,
+            label = label,
+            message = message,
+            label_width = label_width,
+            message_width = message_width,
+            label_color = self.label_color,
+            color = self.color,
+            label_x = f64::from(label_width) / 2.0,
+            message_x = f64::from(label_width) + f64::from(message_width) / 2.0,
+            text_y = text_y,
+            shadow_y = text_y + 1,
+        )
+    }
+}
+
+pub fn handler(
+    mut req: HttpRequest,
+    info: Path<String>,
+    q: Query<Params>,
+) -> Result<HttpResponse, ApiError> {
+    let format = Format::from_param(q.format.as_deref())?;
+    param::version(q.version.as_deref())?;
+    let style = Style::from_param(q.style.as_deref())?;
+    let color = color(q.color.as_deref().unwrap_or("blue"))?;
+    let label_color = self::color(q.label_color.as_deref().unwrap_or("grey"))?;
+    let label = q.label.as_deref().unwrap_or("release");
+    let sha = param::sha(&info)?;
+    access::shas(&mut req, 1);
+    let name = metrics::lookup(sha)
+        .map(|name| name.with_case(*format).to_string())
+        .map_err(|err| ApiError::lookup(sha, err))?;
+
+    // Only a sha that can be named has a badge to cache.
+    let etag = Etag::from_parts(&[
+        "badge",
+        &format!("{:?}", *format),
+        &format!("{:?}", style),
+        &color,
+        &label_color,
+        label,
+        sha,
+    ]);
+    if etag.matches(&req) {
+        return Ok(etag.not_modified());
+    }
+
+    let badge = Badge {
+        label,
+        message: &name,
+        color: &color,
+        label_color: &label_color,
+        style,
+    };
+    let resp = HttpResponse::build(StatusCode::OK)
+        .header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("image/svg+xml; charset=utf-8"),
+        )
+        .body(badge.render());
+    Ok(etag.apply(resp))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::http::Method;
+    use testing::{send, server};
+
+    #[test]
+    fn colors_can_be_named_or_hex() {
+        assert_eq!(color("brightgreen"), Ok(String::from("#4c1")));
+        assert_eq!(color("FF69B4"), Ok(String::from("#ff69b4")));
+        assert_eq!(color("#abc"), Ok(String::from("#abc")));
+        assert!(color("chartreuse").is_err());
+        assert!(color("\"/><script>").is_err());
+    }
+
+    #[test]
+    fn text_width_is_estimated_per_character() {
+        assert_eq!(text_width(""), 0.0);
+        assert!(text_width("mmm") > text_width("iii"));
+        assert!((text_width("release") - 39.7).abs() < 0.01);
+        assert_eq!(Badge::section_width("release"), 50);
+    }
+
+    #[test]
+    fn badges_escape_their_text() {
+        let badge = Badge {
+            label: "<deploy>",
+            message: "ambitiously timeless gemot",
+            color: "#007ec6",
+            label_color: "#555",
+            style: Style::FlatSquare,
+        };
+        let svg = badge.render();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("<title>&lt;deploy&gt;: ambitiously timeless gemot</title>"));
+        assert!(svg.contains("rx=\"0\""));
+        assert!(!svg.contains("<deploy>"));
+    }
+
+    #[test]
+    fn styles_are_validated() {
+        assert_eq!(Style::from_param(None), Ok(Style::Flat));
+        assert_eq!(Style::from_param(Some("plastic")), Ok(Style::Plastic));
+        assert!(Style::from_param(Some("for-the-badge")).is_err());
+    }
+
+    #[test]
+    fn only_named_shas_are_cached() {
+        let mut srv = server();
+        let req = srv
+            .client(Method::GET, "/api/release-name/xyz/badge.svg")
+            .header(header::IF_NONE_MATCH, "*")
+            .finish()
+            .unwrap();
+        let (status, headers, _) = send(&mut srv, req);
+        assert_eq!(status, 400);
+        assert!(headers.get(header::ETAG).is_none());
+
+        let req = srv
+            .client(Method::GET, "/api/release-name/abc/badge.svg")
+            .finish()
+            .unwrap();
+        let (status, headers, _) = send(&mut srv, req);
+        assert_eq!(status, 200);
+        let req = srv
+            .client(Method::GET, "/api/release-name/abc/badge.svg")
+            .header(header::IF_NONE_MATCH, headers[header::ETAG].clone())
+            .finish()
+            .unwrap();
+        assert_eq!(send(&mut srv, req).0, 304);
+    }
+}
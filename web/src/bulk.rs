@@ -0,0 +1,223 @@
+use super::Response;
+use access;
+use actix_web::error::JsonPayloadError;
+use actix_web::{Error, HttpRequest, HttpResponse, Json};
+use error::ApiError;
+use git_release_name::Case;
+use limits::Limits;
+use metrics;
+use openapi::{self, Parameters, Schema};
+use output::{Output, Row};
+use param::{self, Format, Query};
+use serde_json::Value;
+
+#[derive(Deserialize)]
+pub struct Params {
+    format: Option<String>,
+    version: Option<String>,
+    output: Option<String>,
+}
+
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![openapi::format(), openapi::version(), openapi::output()]
+    }
+}
+
+/// The request body is either a bare list of shas or an object holding the
+/// shas along with options that take precedence over the query string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Body {
+    List(Vec<String>),
+    Options {
+        shas: Vec<String>,
+        format: Option<String>,
+        version: Option<u32>,
+    },
+}
+
+impl Body {
+    fn shas(&self) -> &[String] {
+        match *self {
+            Body::List(ref shas) | Body::Options { ref shas, .. } => shas,
+        }
+    }
+
+    fn format(&self) -> Option<&str> {
+        match *self {
+            Body::Options { ref format, .. } => format.as_deref(),
+            Body::List(_) => None,
+        }
+    }
+
+    fn version(&self) -> Option<String> {
+        match *self {
+            Body::Options { version, .. } => version.map(|v| v.to_string()),
+            Body::List(_) => None,
+        }
+    }
+}
+
+impl Schema for Body {
+    fn schema() -> Value {
+        let shas = openapi::array(openapi::string());
+        json!({
+            "oneOf": [
+                shas,
+                openapi::object(
+                    &[
+                        ("shas", shas.clone()),
+                        ("format", Format::schema()),
+                        ("version", openapi::integer()),
+                    ],
+                    &["format", "version"],
+                ),
+            ]
+        })
+    }
+}
+
+/// The result of naming one of the requested shas.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Record {
+    sha: String,
+    name: Option<String>,
+    error: Option<ApiError>,
+}
+
+impl Record {
+    fn new(case: Case, sha: &str) -> Self {
+        match metrics::lookup(sha) {
+            Ok(name) => Record {
+                sha: sha.to_string(),
+                name: Some(name.with_case(case).to_string()),
+                error: None,
+            },
+            Err(err) => Record {
+                sha: sha.to_string(),
+                name: None,
+                error: Some(ApiError::lookup(sha, err)),
+            },
+        }
+    }
+}
+
+impl Schema for Record {
+    fn schema() -> Value {
+        openapi::object(
+            &[
+                ("sha", openapi::string()),
+                ("name", openapi::nullable(openapi::string())),
+                ("error", openapi::nullable(ApiError::schema())),
+            ],
+            &[],
+        )
+    }
+}
+
+/// Names every sha in the order given.
+fn records(case: Case, shas: &[String]) -> Vec<Record> {
+    shas.iter().map(|sha| Record::new(case, sha)).collect()
+}
+
+pub fn handler(
+    mut req: HttpRequest,
+    q: Query<Params>,
+    body: Json<Body>,
+) -> Result<HttpResponse, ApiError> {
+    let format = Format::from_param(body.format().or(q.format.as_deref()))?;
+    param::version(body.version().as_deref().or(q.version.as_deref()))?;
+    let output = Output::negotiate(&req, q.output.as_deref(), Output::Json)?;
+    let limits = Limits::of(&req);
+    let shas = limits.shas(body.shas().iter().map(String::as_str), limits.bulk_shas)?;
+    metrics::bulk_shas("bulk", shas.len());
+    access::shas(&mut req, shas.len());
+
+    let records = records(*format, &shas);
+    let rows: Vec<Row> = records
+        .iter()
+        .map(|record| Row::new(&record.sha, record.name.as_deref()))
+        .collect();
+    Ok(output.respond(&Response::new(&records), &rows))
+}
+
+/// Reports problems reading the body with an `ApiError`.
+pub fn body_error(err: JsonPayloadError, req: HttpRequest) -> Error {
+    payload_error(err, &req).into()
+}
+
+/// The `ApiError` for a problem reading the body.
+pub fn payload_error<S>(err: JsonPayloadError, req: &HttpRequest<S>) -> ApiError {
+    match err {
+        JsonPayloadError::Overflow => ApiError::body_too_large(Limits::of(req).body),
+        err => ApiError::invalid_body(&err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::http::Method;
+    use serde_json;
+    use testing::{fetch, server};
+
+    #[test]
+    fn records_keep_order_and_duplicates() {
+        let shas = vec![
+            String::from("xyz"),
+            String::from("abc"),
+            String::from("abc"),
+        ];
+        let records = records(Case::Kebab, &shas);
+        assert_eq!(
+            serde_json::to_value(&records).unwrap(),
+            json!([
+                {
+                    "sha": "xyz",
+                    "name": null,
+                    "error": {
+                        "code": "non_hexadecimal",
+                        "message": "Shas may only contain hexadecimal characters",
+                        "value": "xyz",
+                    },
+                },
+                { "sha": "abc", "name": "ambitiously-timeless-gemot", "error": null },
+                { "sha": "abc", "name": "ambitiously-timeless-gemot", "error": null },
+            ])
+        );
+    }
+
+    #[test]
+    fn bodies_can_be_lists_or_objects() {
+        let body: Body = serde_json::from_str(r#"["abc", "123"]"#).unwrap();
+        assert_eq!(body.shas(), &["abc", "123"]);
+        assert_eq!(body.format(), None);
+
+        let body: Body =
+            serde_json::from_str(r#"{"shas": ["abc"], "format": "snake", "version": 1}"#).unwrap();
+        assert_eq!(body.shas(), &["abc"]);
+        assert_eq!(body.format(), Some("snake"));
+        assert_eq!(body.version().as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn versions_are_integers_as_documented() {
+        let mut srv = server();
+        let (status, body) = fetch(
+            &mut srv,
+            Method::POST,
+            "/api/release-name",
+            r#"{"shas": ["abc"], "version": 1}"#,
+        );
+        assert_eq!(status, 200, "{}", body);
+        let (status, body) = fetch(
+            &mut srv,
+            Method::POST,
+            "/api/release-name",
+            r#"{"shas": ["abc"], "version": 0}"#,
+        );
+        assert_eq!(status, 400);
+        assert!(body.contains("unknown_dictionary_version"), "{}", body);
+    }
+}
@@ -0,0 +1,121 @@
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{http::StatusCode, HttpMessage, HttpRequest, HttpResponse};
+use git_release_name::{Case, HashAlgorithm, DICTIONARY_VERSION};
+use output::Output;
+
+/// How long, in seconds, shared caches may keep a name. A sha always gets the
+/// same name from a dictionary version so this is as long as is allowed.
+pub const MAX_AGE: u32 = 31_536_000;
+
+/// A strong entity tag for a response naming the given shas.
+///
+/// The tag covers everything that changes the body: the shas in order, the
+/// case, the output format and the dictionary version.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Etag(String);
+
+impl Etag {
+    pub fn new<S: AsRef<str>>(shas: &[S], case: Case, output: Output) -> Self {
+        let mut parts = vec![format!("{:?}", case), format!("{:?}", output)];
+        parts.extend(shas.iter().map(|sha| sha.as_ref().to_string()));
+        Etag::from_parts(&parts)
+    }
+
+    /// A tag for a response built from the given parts, in order, and the
+    /// dictionary version.
+    pub fn from_parts<S: AsRef<str>>(parts: &[S]) -> Self {
+        let mut key = DICTIONARY_VERSION.to_string();
+        for part in parts {
+            key.push('\n');
+            key.push_str(part.as_ref());
+        }
+        let digest = HashAlgorithm::current().hex_digest(key.as_bytes());
+        Etag(format!("\"{}\"", digest))
+    }
+
+    /// Whether the client already holds this version of the response. A `*`
+    /// matches any current representation, as RFC 7232 has it, so it is
+    /// only checked once the response is known to exist.
+    pub fn matches<S>(&self, req: &HttpRequest<S>) -> bool {
+        req.headers()
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim())
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == self.0)
+    }
+
+    /// An empty response telling the client to use its copy.
+    pub fn not_modified(&self) -> HttpResponse {
+        self.apply(HttpResponse::build(StatusCode::NOT_MODIFIED).finish())
+    }
+
+    /// Marks a response as cacheable forever by shared caches.
+    pub fn apply(&self, mut resp: HttpResponse) -> HttpResponse {
+        {
+            let headers = resp.headers_mut();
+            headers.insert(
+                header::ETAG,
+                HeaderValue::from_str(&self.0).expect("etags are hexadecimal"),
+            );
+            headers.insert(
+                header::CACHE_CONTROL,
+                HeaderValue::from_str(&format!("public, max-age={}, immutable", MAX_AGE))
+                    .expect("cache control is ascii"),
+            );
+            // The body depends on the Accept header as well as the url.
+            headers.insert(header::VARY, HeaderValue::from_static("Accept"));
+        }
+        resp
+    }
+}
+
+/// Marks a response that must never be reused.
+pub fn no_store(mut resp: HttpResponse) -> HttpResponse {
+    resp.headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    resp
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn tags_change_with_the_representation() {
+        let tag = Etag::new(&["abc"], Case::Lower, Output::Text);
+        assert_eq!(tag, Etag::new(&["abc"], Case::Lower, Output::Text));
+        assert!(tag.0.starts_with('"') && tag.0.ends_with('"'));
+        assert_ne!(tag, Etag::new(&["abd"], Case::Lower, Output::Text));
+        assert_ne!(tag, Etag::new(&["abc"], Case::Upper, Output::Text));
+        assert_ne!(tag, Etag::new(&["abc"], Case::Lower, Output::Json));
+        assert_ne!(
+            Etag::new(&["abc", "123"], Case::Lower, Output::Json),
+            Etag::new(&["123", "abc"], Case::Lower, Output::Json)
+        );
+    }
+
+    #[test]
+    fn tags_match_if_none_match() {
+        let tag = Etag::new(&["abc"], Case::Lower, Output::Text);
+        let header = format!("\"other\", W/{}", tag.0);
+        assert!(tag.matches(&TestRequest::with_header("If-None-Match", header).finish()));
+        assert!(tag.matches(&TestRequest::with_header("If-None-Match", "*").finish()));
+        assert!(!tag.matches(&TestRequest::with_header("If-None-Match", "\"other\"").finish()));
+        assert!(!tag.matches(&TestRequest::default().finish()));
+    }
+
+    #[test]
+    fn not_modified_responses_keep_the_headers() {
+        let tag = Etag::new(&["abc"], Case::Lower, Output::Text);
+        let resp = tag.not_modified();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers()[header::ETAG], tag.0.as_str());
+        assert_eq!(
+            resp.headers()[header::CACHE_CONTROL],
+            "public, max-age=31536000, immutable"
+        );
+    }
+}
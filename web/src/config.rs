@@ -0,0 +1,737 @@
+use access::{Level, LogFormat};
+use auth::{Proxy, PUBLIC_ROUTES};
+use clap::{App, Arg, ArgMatches};
+use limits::{Duplicates, Limits};
+use openapi;
+use ratelimit::{Rate, ANY_ROUTE};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml;
+
+/// Settings for the server. They are read from an optional TOML file and can
+/// be overridden by environment variables and command line flags, in that
+/// order.
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The address to listen on
+    pub address: String,
+    /// The port to listen on
+    pub port: u16,
+    /// A unix domain socket to listen on instead of a tcp address
+    pub socket: Option<PathBuf>,
+    /// The number of worker threads, defaults to the number of cpus
+    pub workers: Option<usize>,
+    /// Seconds to keep idle connections open, zero disables keep-alive
+    pub keep_alive: Option<usize>,
+    /// Seconds a request may take once its head has arrived, zero disables
+    /// the timeout
+    pub request_timeout: Option<u64>,
+    /// Seconds to wait for in flight requests when shutting down
+    pub shutdown_timeout: Option<u16>,
+    /// How access log lines are written
+    pub log_format: LogFormat,
+    /// The least severe access log lines that are written
+    pub log_level: Level,
+    /// A SQLite file to keep name reservations in, which enables the
+    /// reservation routes
+    pub reservations: Option<PathBuf>,
+    /// The secret push webhooks are signed with, which enables the webhook
+    /// route
+    pub webhook_secret: Option<String>,
+    /// An http url to post a summary of every named push to
+    pub webhook_forward: Option<String>,
+    /// The Slack signing secret or Mattermost token of the slash command,
+    /// which enables the slash command route
+    pub slash_secret: Option<String>,
+    /// The most shas that can be named from a query string
+    pub max_shas: usize,
+    /// The most shas that can be named from a request body
+    pub max_bulk_shas: usize,
+    /// The longest a single sha may be
+    pub max_sha_length: usize,
+    /// The largest request body in bytes
+    pub max_body: usize,
+    /// The longest query string in bytes
+    pub max_query: usize,
+    /// What to do with shas that are requested more than once
+    pub duplicates: Duplicates,
+    /// A file of API keys, one `name:sha256` per line
+    pub api_keys_file: Option<PathBuf>,
+    /// API keys as `name:sha256`, in addition to the file
+    pub api_keys: Vec<String>,
+    /// Whether requests must carry an API key
+    pub require_api_key: bool,
+    /// Routes that are served without an API key when keys are required
+    pub public_routes: Vec<String>,
+    /// The rate each client may call a route at, by route name, with `*`
+    /// for every other route
+    pub rate_limits: BTreeMap<String, Rate>,
+    /// Proxies whose `X-Forwarded-For` header names the client, by address
+    /// or `unix` for the unix socket
+    pub trusted_proxies: Vec<Proxy>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let limits = Limits::default();
+        Config {
+            address: String::from("0.0.0.0"),
+            port: 6767,
+            socket: None,
+            workers: None,
+            keep_alive: None,
+            request_timeout: None,
+            shutdown_timeout: None,
+            log_format: LogFormat::Logfmt,
+            log_level: Level::Info,
+            reservations: None,
+            webhook_secret: None,
+            webhook_forward: None,
+            slash_secret: None,
+            max_shas: limits.shas,
+            max_bulk_shas: limits.bulk_shas,
+            max_sha_length: limits.sha_length,
+            max_body: limits.body,
+            max_query: limits.query,
+            duplicates: limits.duplicates,
+            api_keys_file: None,
+            api_keys: Vec::new(),
+            require_api_key: false,
+            public_routes: PUBLIC_ROUTES
+                .iter()
+                .map(|route| route.to_string())
+                .collect(),
+            rate_limits: BTreeMap::new(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+/// Failures when loading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file could not be read
+    Io(PathBuf, io::Error),
+    /// The config file is not valid
+    Toml(PathBuf, toml::de::Error),
+    /// A flag or environment variable has an invalid value
+    Invalid(&'static str, String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Toml(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Invalid(name, ref value) => {
+                write!(f, "invalid value for {}: {:?}", name, value)
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the process arguments and environment.
+    pub fn load() -> Result<Config, ConfigError> {
+        Config::from_matches(&app().get_matches())
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Config, ConfigError> {
+        let mut config = match matches.value_of("config") {
+            Some(path) => Config::from_file(Path::new(path))?,
+            None => Config::default(),
+        };
+
+        if let Some(address) = matches.value_of("address") {
+            config.address = address.to_string();
+        }
+        if let Some(port) = parse(matches, "port")? {
+            config.port = port;
+        }
+        if let Some(socket) = matches.value_of("socket") {
+            config.socket = Some(PathBuf::from(socket));
+        }
+        if let Some(workers) = parse(matches, "workers")? {
+            config.workers = Some(workers);
+        }
+        if let Some(keep_alive) = parse(matches, "keep-alive")? {
+            config.keep_alive = Some(keep_alive);
+        }
+        if let Some(timeout) = parse(matches, "request-timeout")? {
+            config.request_timeout = Some(timeout);
+        }
+        if let Some(timeout) = parse(matches, "shutdown-timeout")? {
+            config.shutdown_timeout = Some(timeout);
+        }
+        if let Some(format) = parse(matches, "log-format")? {
+            config.log_format = format;
+        }
+        if let Some(level) = parse(matches, "log-level")? {
+            config.log_level = level;
+        }
+        if let Some(reservations) = matches.value_of("reservations") {
+            config.reservations = Some(PathBuf::from(reservations));
+        }
+        if let Some(secret) = matches.value_of("webhook-secret") {
+            config.webhook_secret = Some(secret.to_string());
+        }
+        if let Some(url) = matches.value_of("webhook-forward") {
+            config.webhook_forward = Some(url.to_string());
+        }
+        if let Some(secret) = matches.value_of("slash-secret") {
+            config.slash_secret = Some(secret.to_string());
+        }
+        if let Some(max) = parse(matches, "max-shas")? {
+            config.max_shas = max;
+        }
+        if let Some(max) = parse(matches, "max-bulk-shas")? {
+            config.max_bulk_shas = max;
+        }
+        if let Some(max) = parse(matches, "max-sha-length")? {
+            config.max_sha_length = max;
+        }
+        if let Some(max) = parse(matches, "max-body")? {
+            config.max_body = max;
+        }
+        if let Some(max) = parse(matches, "max-query")? {
+            config.max_query = max;
+        }
+        if let Some(duplicates) = parse(matches, "duplicates")? {
+            config.duplicates = duplicates;
+        }
+        if let Some(file) = matches.value_of("api-keys-file") {
+            config.api_keys_file = Some(PathBuf::from(file));
+        }
+        if let Some(keys) = matches.value_of("api-keys") {
+            config.api_keys = list(keys).map(String::from).collect();
+        }
+        if let Some(required) = parse(matches, "require-api-key")? {
+            config.require_api_key = required;
+        }
+        if let Some(routes) = matches.value_of("public-routes") {
+            config.public_routes = list(routes).map(String::from).collect();
+        }
+        if let Some(limits) = matches.value_of("rate-limits") {
+            config.rate_limits = list(limits)
+                .map(|limit| {
+                    limit
+                        .split_once('=')
+                        .and_then(|(route, rate)| {
+                            Some((route.trim().to_string(), rate.parse().ok()?))
+                        })
+                        .ok_or_else(|| ConfigError::Invalid("rate-limits", limit.to_string()))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(proxies) = matches.value_of("trusted-proxies") {
+            config.trusted_proxies = list(proxies)
+                .map(|proxy| {
+                    proxy
+                        .parse()
+                        .map_err(|_| ConfigError::Invalid("trusted-proxies", proxy.to_string()))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        for route in &config.public_routes {
+            if !is_route(route) {
+                return Err(ConfigError::Invalid("public-routes", route.clone()));
+            }
+        }
+        for route in config.rate_limits.keys() {
+            if route != ANY_ROUTE && !is_route(route) {
+                return Err(ConfigError::Invalid("rate-limits", route.clone()));
+            }
+        }
+        // An empty secret would let through every webhook or slash command
+        // sent without a signature or token.
+        if config.webhook_secret.as_deref() == Some("") {
+            return Err(ConfigError::Invalid("webhook-secret", String::new()));
+        }
+        if config.slash_secret.as_deref() == Some("") {
+            return Err(ConfigError::Invalid("slash-secret", String::new()));
+        }
+        // Only plain http can be forwarded to, since the client is built
+        // without tls.
+        if let Some(ref url) = config.webhook_forward {
+            if !url.starts_with("http://") {
+                return Err(ConfigError::Invalid("webhook-forward", url.clone()));
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        toml::from_str(&contents).map_err(|e| ConfigError::Toml(path.to_path_buf(), e))
+    }
+
+    /// The limits on what a single request may ask for.
+    pub fn limits(&self) -> Limits {
+        Limits {
+            shas: self.max_shas,
+            bulk_shas: self.max_bulk_shas,
+            sha_length: self.max_sha_length,
+            body: self.max_body,
+            query: self.max_query,
+            duplicates: self.duplicates,
+        }
+    }
+
+    /// How long a request may take, if it is limited.
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+            .filter(|&timeout| timeout > 0)
+            .map(Duration::from_secs)
+    }
+
+    /// The tcp address to bind to.
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.address, self.port)
+    }
+}
+
+/// Splits a comma separated flag, dropping blank entries.
+fn list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Whether a name is one of the routes of the app.
+fn is_route(name: &str) -> bool {
+    name == "unmatched" || openapi::routes().iter().any(|route| route.name == name)
+}
+
+fn parse<T: ::std::str::FromStr>(
+    matches: &ArgMatches,
+    name: &'static str,
+) -> Result<Option<T>, ConfigError> {
+    match matches.value_of(name) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| ConfigError::Invalid(name, value.to_string())),
+        None => Ok(None),
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("Git Release Names Web")
+        .author("Kevin Choubacha <chewbacha@gmail.com>")
+        .about("Serves release names over http")
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .short("c")
+                .env("RELEASE_NAME_CONFIG")
+                .takes_value(true)
+                .help("Path to a TOML config file."),
+        )
+        .arg(
+            Arg::with_name("address")
+                .long("address")
+                .short("a")
+                .env("RELEASE_NAME_ADDRESS")
+                .takes_value(true)
+                .help("The address to listen on. [default: 0.0.0.0]"),
+        )
+        .arg(
+            Arg::with_name("port")
+                .long("port")
+                .short("p")
+                .env("RELEASE_NAME_PORT")
+                .takes_value(true)
+                .help("The port to listen on. [default: 6767]"),
+        )
+        .arg(
+            Arg::with_name("socket")
+                .long("socket")
+                .short("s")
+                .env("RELEASE_NAME_SOCKET")
+                .takes_value(true)
+                .help("Listen on a unix domain socket instead of an address and port."),
+        )
+        .arg(
+            Arg::with_name("workers")
+                .long("workers")
+                .short("w")
+                .env("RELEASE_NAME_WORKERS")
+                .takes_value(true)
+                .help("The number of worker threads. [default: number of cpus]"),
+        )
+        .arg(
+            Arg::with_name("keep-alive")
+                .long("keep-alive")
+                .env("RELEASE_NAME_KEEP_ALIVE")
+                .takes_value(true)
+                .help("Seconds to keep idle connections open, 0 disables keep-alive."),
+        )
+        .arg(
+            Arg::with_name("request-timeout")
+                .long("request-timeout")
+                .env("RELEASE_NAME_REQUEST_TIMEOUT")
+                .takes_value(true)
+                .help(
+                    "Seconds a request may take once its head has arrived, 0 disables the timeout.",
+                ),
+        )
+        .arg(
+            Arg::with_name("shutdown-timeout")
+                .long("shutdown-timeout")
+                .env("RELEASE_NAME_SHUTDOWN_TIMEOUT")
+                .takes_value(true)
+                .help("Seconds to wait for in flight requests when shutting down."),
+        )
+        .arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .env("RELEASE_NAME_LOG_FORMAT")
+                .takes_value(true)
+                .help("Access log format, json or logfmt. [default: logfmt]"),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .env("RELEASE_NAME_LOG_LEVEL")
+                .takes_value(true)
+                .help("Access log level, debug, info, warn, error or off. [default: info]"),
+        )
+        .arg(
+            Arg::with_name("reservations")
+                .long("reservations")
+                .env("RELEASE_NAME_RESERVATIONS")
+                .takes_value(true)
+                .help("A SQLite file to keep reservations in. Reservations are off without one."),
+        )
+        .arg(
+            Arg::with_name("webhook-secret")
+                .long("webhook-secret")
+                .env("RELEASE_NAME_WEBHOOK_SECRET")
+                .takes_value(true)
+                .hide_env_values(true)
+                .help("The secret push webhooks are signed with. Webhooks are off without one."),
+        )
+        .arg(
+            Arg::with_name("webhook-forward")
+                .long("webhook-forward")
+                .env("RELEASE_NAME_WEBHOOK_FORWARD")
+                .takes_value(true)
+                .help("An http url to post a summary of every named push to."),
+        )
+        .arg(
+            Arg::with_name("slash-secret")
+                .long("slash-secret")
+                .env("RELEASE_NAME_SLASH_SECRET")
+                .takes_value(true)
+                .hide_env_values(true)
+                .help("The Slack signing secret or Mattermost token of the slash command."),
+        )
+        .arg(
+            Arg::with_name("max-shas")
+                .long("max-shas")
+                .env("RELEASE_NAME_MAX_SHAS")
+                .takes_value(true)
+                .help("The most shas named from a query string. [default: 1000]"),
+        )
+        .arg(
+            Arg::with_name("max-bulk-shas")
+                .long("max-bulk-shas")
+                .env("RELEASE_NAME_MAX_BULK_SHAS")
+                .takes_value(true)
+                .help("The most shas named from a request body. [default: 10000]"),
+        )
+        .arg(
+            Arg::with_name("max-sha-length")
+                .long("max-sha-length")
+                .env("RELEASE_NAME_MAX_SHA_LENGTH")
+                .takes_value(true)
+                .help("The longest a single sha may be. [default: 64]"),
+        )
+        .arg(
+            Arg::with_name("max-body")
+                .long("max-body")
+                .env("RELEASE_NAME_MAX_BODY")
+                .takes_value(true)
+                .help("The largest request body in bytes. [default: 1048576]"),
+        )
+        .arg(
+            Arg::with_name("max-query")
+                .long("max-query")
+                .env("RELEASE_NAME_MAX_QUERY")
+                .takes_value(true)
+                .help("The longest query string in bytes. [default: 65536]"),
+        )
+        .arg(
+            Arg::with_name("duplicates")
+                .long("duplicates")
+                .env("RELEASE_NAME_DUPLICATES")
+                .takes_value(true)
+                .help("Shas requested twice are kept, merged or rejected. [default: keep]"),
+        )
+        .arg(
+            Arg::with_name("api-keys-file")
+                .long("api-keys-file")
+                .env("RELEASE_NAME_API_KEYS_FILE")
+                .takes_value(true)
+                .help("A file of API keys, one name:sha256 per line."),
+        )
+        .arg(
+            Arg::with_name("api-keys")
+                .long("api-keys")
+                .env("RELEASE_NAME_API_KEYS")
+                .takes_value(true)
+                .hide_env_values(true)
+                .help("Comma separated API keys as name:sha256."),
+        )
+        .arg(
+            Arg::with_name("require-api-key")
+                .long("require-api-key")
+                .env("RELEASE_NAME_REQUIRE_API_KEY")
+                .takes_value(true)
+                .help("Whether requests must carry an API key, true or false. [default: false]"),
+        )
+        .arg(
+            Arg::with_name("public-routes")
+                .long("public-routes")
+                .env("RELEASE_NAME_PUBLIC_ROUTES")
+                .takes_value(true)
+                .help("Comma separated routes served without an API key."),
+        )
+        .arg(
+            Arg::with_name("rate-limits")
+                .long("rate-limits")
+                .env("RELEASE_NAME_RATE_LIMITS")
+                .takes_value(true)
+                .help("Comma separated route=rate limits, such as random=60/minute,*=600/minute."),
+        )
+        .arg(
+            Arg::with_name("trusted-proxies")
+                .long("trusted-proxies")
+                .env("RELEASE_NAME_TRUSTED_PROXIES")
+                .takes_value(true)
+                .help("Comma separated proxy addresses, or unix, trusted to send X-Forwarded-For."),
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(args: &[&str]) -> Result<Config, ConfigError> {
+        let args = ::std::iter::once("git-release-name-web").chain(args.iter().cloned());
+        Config::from_matches(&app().get_matches_from(args))
+    }
+
+    #[test]
+    fn it_defaults_to_the_original_address() {
+        assert_eq!(config(&[]).unwrap().addr(), "0.0.0.0:6767");
+    }
+
+    #[test]
+    fn flags_override_the_defaults() {
+        let config = config(&[
+            "--address",
+            "127.0.0.1",
+            "--port",
+            "8080",
+            "--workers",
+            "2",
+            "--keep-alive",
+            "0",
+        ])
+        .unwrap();
+        assert_eq!(config.addr(), "127.0.0.1:8080");
+        assert_eq!(config.workers, Some(2));
+        assert_eq!(config.keep_alive, Some(0));
+    }
+
+    #[test]
+    fn requests_can_be_given_a_timeout() {
+        let config = config(&["--request-timeout", "30"]).unwrap();
+        assert_eq!(config.request_timeout(), Some(Duration::from_secs(30)));
+        let config = self::config(&["--request-timeout", "0"]).unwrap();
+        assert_eq!(config.request_timeout(), None);
+        assert_eq!(Config::default().request_timeout(), None);
+        let config: Config = toml::from_str("request_timeout = 10").unwrap();
+        assert_eq!(config.request_timeout, Some(10));
+        match self::config(&["--request-timeout", "soon"]) {
+            Err(ConfigError::Invalid("request-timeout", _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_numbers_are_rejected() {
+        match config(&["--port", "eighty"]) {
+            Err(ConfigError::Invalid("port", ref value)) if value == "eighty" => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn webhooks_only_forward_over_http() {
+        let config = config(&[
+            "--webhook-secret",
+            "hunter2",
+            "--webhook-forward",
+            "http://localhost:8065/hooks/abc",
+        ])
+        .unwrap();
+        assert_eq!(config.webhook_secret.as_deref(), Some("hunter2"));
+        match self::config(&["--webhook-forward", "https://chat.example.com/hooks/abc"]) {
+            Err(ConfigError::Invalid("webhook-forward", _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn secrets_cannot_be_empty() {
+        match config(&["--webhook-secret", ""]) {
+            Err(ConfigError::Invalid("webhook-secret", _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        let path = ::std::env::temp_dir().join("release-name-empty-secrets.toml");
+        fs::write(&path, "webhook_secret = \"\"\n").unwrap();
+        let loaded = config(&["--config", path.to_str().unwrap()]);
+        match loaded {
+            Err(ConfigError::Invalid("webhook-secret", _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match config(&["--slash-secret", ""]) {
+            Err(ConfigError::Invalid("slash-secret", _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        fs::write(&path, "slash_secret = \"\"\n").unwrap();
+        let loaded = config(&["--config", path.to_str().unwrap()]);
+        fs::remove_file(&path).unwrap();
+        match loaded {
+            Err(ConfigError::Invalid("slash-secret", _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn files_can_be_deserialized() {
+        let config: Config = toml::from_str(
+            r#"
+            port = 7000
+            socket = "/tmp/release-name.sock"
+            shutdown_timeout = 5
+            log_format = "json"
+            reservations = "/var/lib/release-name/reservations.db"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.address, "0.0.0.0");
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.socket, Some(PathBuf::from("/tmp/release-name.sock")));
+        assert_eq!(config.shutdown_timeout, Some(5));
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.log_level, Level::Info);
+        assert_eq!(
+            config.reservations,
+            Some(PathBuf::from("/var/lib/release-name/reservations.db"))
+        );
+    }
+
+    #[test]
+    fn limits_can_be_configured() {
+        let config = config(&["--max-shas", "10", "--duplicates", "merge"]).unwrap();
+        let limits = config.limits();
+        assert_eq!(limits.shas, 10);
+        assert_eq!(limits.bulk_shas, Limits::default().bulk_shas);
+        assert_eq!(limits.duplicates, Duplicates::Merge);
+        match self::config(&["--duplicates", "drop"]) {
+            Err(ConfigError::Invalid("duplicates", _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        let config: Config = toml::from_str("max_body = 4096\nduplicates = \"reject\"").unwrap();
+        assert_eq!(config.limits().body, 4096);
+        assert_eq!(config.limits().duplicates, Duplicates::Reject);
+    }
+
+    #[test]
+    fn rate_limits_are_given_by_route() {
+        let config = config(&[
+            "--rate-limits",
+            "random=60/minute, *=10/s",
+            "--require-api-key",
+            "true",
+        ])
+        .unwrap();
+        assert!(config.require_api_key);
+        assert_eq!(config.rate_limits["random"].to_string(), "60/minute");
+        assert_eq!(config.rate_limits["*"].to_string(), "10/second");
+        match self::config(&["--rate-limits", "randon=60/minute"]) {
+            Err(ConfigError::Invalid("rate-limits", ref route)) if route == "randon" => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match self::config(&["--rate-limits", "random=often"]) {
+            Err(ConfigError::Invalid("rate-limits", _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match self::config(&["--public-routes", "healthz,everything"]) {
+            Err(ConfigError::Invalid("public-routes", ref route)) if route == "everything" => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keys_and_limits_can_be_read_from_files() {
+        let config: Config = toml::from_str(
+            r#"
+            api_keys_file = "/etc/release-name/keys"
+            require_api_key = true
+            public_routes = ["healthz", "home"]
+
+            [rate_limits]
+            random = "60/minute"
+            "*" = "1200/hour"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.api_keys_file,
+            Some(PathBuf::from("/etc/release-name/keys"))
+        );
+        assert_eq!(config.public_routes, vec!["healthz", "home"]);
+        assert_eq!(config.rate_limits.len(), 2);
+        assert_eq!(
+            Config::default().public_routes,
+            vec!["healthz", "readyz", "metrics", "openapi", "webhook", "slash"]
+        );
+    }
+
+    #[test]
+    fn trusted_proxies_are_addresses_or_the_socket() {
+        let config = config(&["--trusted-proxies", "10.0.0.1, ::1,unix"]).unwrap();
+        assert_eq!(
+            config.trusted_proxies,
+            vec![
+                Proxy::Ip("10.0.0.1".parse().unwrap()),
+                Proxy::Ip("::1".parse().unwrap()),
+                Proxy::Unix,
+            ]
+        );
+        match self::config(&["--trusted-proxies", "10.0.0.0/8"]) {
+            Err(ConfigError::Invalid("trusted-proxies", ref proxy)) if proxy == "10.0.0.0/8" => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        let config: Config = toml::from_str("trusted_proxies = [\"unix\"]").unwrap();
+        assert_eq!(config.trusted_proxies, vec![Proxy::Unix]);
+        assert!(toml::from_str::<Config>("trusted_proxies = [\"proxy\"]").is_err());
+    }
+
+    #[test]
+    fn unknown_file_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("prot = 7000").is_err());
+    }
+}
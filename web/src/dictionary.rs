@@ -0,0 +1,221 @@
+use super::Response;
+use actix_web::{Json, Path};
+use error::ApiError;
+use git_release_name::{self, Entry, Kind};
+use openapi::{self, Parameters, Schema};
+use param::Query;
+use serde_json::Value;
+
+/// The number of entries returned when no limit is given.
+const DEFAULT_LIMIT: usize = 100;
+
+/// The most entries that can be returned at once.
+const MAX_LIMIT: usize = 1000;
+
+/// Parses the kind of word in the path, using the same names as the cli.
+fn kind(kind: &str) -> Result<Kind, ApiError> {
+    match kind {
+        "n" | "nouns" => Ok(Kind::Noun),
+        "adj" | "adjectives" => Ok(Kind::Adj),
+        "adv" | "adverbs" => Ok(Kind::Adv),
+        _ => Err(ApiError::unknown_kind(kind)),
+    }
+}
+
+/// A word in the dictionary and the index a sha uses to pick it.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Word {
+    kind: &'static str,
+    word: String,
+    index: usize,
+}
+
+impl From<Entry> for Word {
+    fn from(entry: Entry) -> Self {
+        Word {
+            kind: match entry.kind {
+                Kind::Noun => "noun",
+                Kind::Adj => "adj",
+                Kind::Adv => "adv",
+            },
+            word: entry.word,
+            index: entry.index,
+        }
+    }
+}
+
+impl Schema for Word {
+    fn schema() -> Value {
+        openapi::object(
+            &[
+                ("kind", openapi::string_enum(&["noun", "adj", "adv"])),
+                ("word", openapi::string()),
+                ("index", openapi::integer()),
+            ],
+            &[],
+        )
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct Params {
+    offset: Option<usize>,
+    limit: Option<usize>,
+    prefix: Option<String>,
+    contains: Option<String>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+}
+
+impl Params {
+    fn matches(&self, word: &str) -> bool {
+        let length = word.chars().count();
+        self.prefix
+            .as_ref()
+            .is_none_or(|p| word.starts_with(p.as_str()))
+            && self
+                .contains
+                .as_ref()
+                .is_none_or(|c| word.contains(c.as_str()))
+            && self.min_length.is_none_or(|min| length >= min)
+            && self.max_length.is_none_or(|max| length <= max)
+    }
+}
+
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![
+            openapi::query("offset", "How many words to skip", openapi::integer()),
+            openapi::query(
+                "limit",
+                "How many words to return",
+                json!({ "type": "integer", "minimum": 0, "maximum": MAX_LIMIT }),
+            ),
+            openapi::query("prefix", "Only words starting with it", openapi::string()),
+            openapi::query("contains", "Only words containing it", openapi::string()),
+            openapi::query("min_length", "The shortest word", openapi::integer()),
+            openapi::query("max_length", "The longest word", openapi::integer()),
+        ]
+    }
+}
+
+/// One page of the words that matched a search.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Page {
+    /// How many words matched, across every page
+    total: usize,
+    offset: usize,
+    limit: usize,
+    words: Vec<Word>,
+}
+
+impl Schema for Page {
+    fn schema() -> Value {
+        openapi::object(
+            &[
+                ("total", openapi::integer()),
+                ("offset", openapi::integer()),
+                ("limit", openapi::integer()),
+                ("words", Vec::<Word>::schema()),
+            ],
+            &[],
+        )
+    }
+}
+
+fn page(kind: Kind, params: &Params) -> Result<Page, ApiError> {
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit > MAX_LIMIT {
+        return Err(ApiError::invalid_query(&format!(
+            "At most {} words may be requested at once",
+            MAX_LIMIT
+        )));
+    }
+
+    let matching: Vec<Entry> = git_release_name::list(kind)
+        .into_iter()
+        .filter(|entry| params.matches(&entry.word))
+        .collect();
+    Ok(Page {
+        total: matching.len(),
+        offset,
+        limit,
+        words: matching
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(Word::from)
+            .collect(),
+    })
+}
+
+/// Lists the words of a kind, a page at a time.
+pub fn index(info: Path<String>, q: Query<Params>) -> Result<Json<Response<Page>>, ApiError> {
+    let kind = kind(&info)?;
+    Ok(Json(Response::new(page(kind, &q)?)))
+}
+
+/// Looks up a single word to find its index.
+pub fn show(info: Path<(String, String)>) -> Result<Json<Response<Word>>, ApiError> {
+    let (ref kind_name, ref word) = *info;
+    git_release_name::list(kind(kind_name)?)
+        .into_iter()
+        .find(|entry| entry.word == *word)
+        .map(|entry| Json(Response::new(Word::from(entry))))
+        .ok_or_else(|| ApiError::unknown_word(word))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kinds_use_the_cli_names() {
+        assert_eq!(kind("nouns"), Ok(Kind::Noun));
+        assert_eq!(kind("adj"), Ok(Kind::Adj));
+        assert_eq!(kind("adverbs"), Ok(Kind::Adv));
+        assert_eq!(kind("verbs"), Err(ApiError::unknown_kind("verbs")));
+    }
+
+    #[test]
+    fn pages_are_sliced_from_the_matches() {
+        let all = page(Kind::Adj, &Params::default()).unwrap();
+        assert_eq!(all.total, git_release_name::list(Kind::Adj).len());
+        assert_eq!(all.words.len(), DEFAULT_LIMIT);
+
+        let params = Params {
+            offset: Some(1),
+            limit: Some(2),
+            ..Params::default()
+        };
+        let second = page(Kind::Adj, &params).unwrap();
+        assert_eq!(second.words[0], all.words[1]);
+        assert_eq!(second.words.len(), 2);
+    }
+
+    #[test]
+    fn words_can_be_searched() {
+        let params = Params {
+            prefix: Some(String::from("ti")),
+            contains: Some(String::from("me")),
+            max_length: Some(8),
+            ..Params::default()
+        };
+        let page = page(Kind::Adj, &params).unwrap();
+        assert!(page.total > 0);
+        assert!(page
+            .words
+            .iter()
+            .all(|w| w.word.starts_with("ti") && w.word.contains("me") && w.word.len() <= 8));
+    }
+
+    #[test]
+    fn limits_are_capped() {
+        let params = Params {
+            limit: Some(MAX_LIMIT + 1),
+            ..Params::default()
+        };
+        assert!(page(Kind::Noun, &params).is_err());
+    }
+}
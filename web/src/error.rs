@@ -0,0 +1,424 @@
+use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use failure::Fail;
+use git_release_name::ParsePhraseError;
+use openapi::{self, Schema};
+use serde_json::{self, Value};
+use std::fmt::{self, Display, Formatter};
+
+/// Machine readable codes for every error the api can return.
+#[derive(Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Code {
+    /// A sha contained non hexadecimal characters
+    NonHexadecimal,
+    /// The requested case format is not known
+    UnknownFormat,
+    /// The requested output is not known
+    UnknownOutput,
+    /// More shas were requested than are allowed
+    TooManyShas,
+    /// A sha is longer than allowed
+    ShaTooLong,
+    /// A sha was requested more than once and duplicates are rejected
+    DuplicateSha,
+    /// The requested dictionary version is not served
+    UnknownDictionaryVersion,
+    /// The query string could not be understood
+    InvalidQuery,
+    /// The request body could not be understood
+    InvalidBody,
+    /// The request body is larger than allowed
+    BodyTooLarge,
+    /// The query string is longer than allowed
+    QueryTooLong,
+    /// No route matches the path
+    NotFound,
+    /// The kind of word is not known
+    UnknownKind,
+    /// The word is not in the dictionary
+    UnknownWord,
+    /// The name could not be looked up
+    LookupFailed,
+    /// Not enough free names fit the constraints
+    NoFreeNames,
+    /// Finding the names took more attempts than a request may make
+    TooManyAttempts,
+    /// The name is not reserved
+    UnknownReservation,
+    /// The name is reserved by another owner
+    NotOwner,
+    /// The reservation store could not be read or written
+    StorageFailed,
+    /// The webhook was not sent by a known forge
+    UnknownEvent,
+    /// The signature or token of a webhook or slash command does not match
+    /// the secret
+    InvalidSignature,
+    /// No API key was sent but one is required
+    MissingApiKey,
+    /// The API key is not one of the keys
+    InvalidApiKey,
+    /// The client has made too many requests to the route
+    RateLimited,
+}
+
+/// Every code, for describing them in the OpenAPI document.
+const CODES: &[Code] = &[
+    Code::NonHexadecimal,
+    Code::UnknownFormat,
+    Code::UnknownOutput,
+    Code::TooManyShas,
+    Code::ShaTooLong,
+    Code::DuplicateSha,
+    Code::UnknownDictionaryVersion,
+    Code::InvalidQuery,
+    Code::InvalidBody,
+    Code::BodyTooLarge,
+    Code::QueryTooLong,
+    Code::NotFound,
+    Code::UnknownKind,
+    Code::UnknownWord,
+    Code::LookupFailed,
+    Code::NoFreeNames,
+    Code::TooManyAttempts,
+    Code::UnknownReservation,
+    Code::NotOwner,
+    Code::StorageFailed,
+    Code::UnknownEvent,
+    Code::InvalidSignature,
+    Code::MissingApiKey,
+    Code::InvalidApiKey,
+    Code::RateLimited,
+];
+
+/// An error returned by the api. It is serialized inside of an `error` key,
+/// the same way successful responses are wrapped in a `data` key.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct ApiError {
+    code: Code,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse<'a> {
+    error: &'a ApiError,
+}
+
+impl ApiError {
+    fn new(code: Code, message: String, value: Option<String>) -> Self {
+        Self {
+            code,
+            message,
+            value,
+        }
+    }
+
+    pub fn non_hexadecimal(sha: &str) -> Self {
+        Self::new(
+            Code::NonHexadecimal,
+            String::from("Shas may only contain hexadecimal characters"),
+            Some(sha.to_string()),
+        )
+    }
+
+    pub fn unknown_format(format: &str) -> Self {
+        Self::new(
+            Code::UnknownFormat,
+            String::from("Unknown case format"),
+            Some(format.to_string()),
+        )
+    }
+
+    pub fn unknown_output(output: &str) -> Self {
+        Self::new(
+            Code::UnknownOutput,
+            String::from("Unknown output, expected text, json, csv or html"),
+            Some(output.to_string()),
+        )
+    }
+
+    pub fn too_many_shas(count: usize, max: usize) -> Self {
+        Self::new(
+            Code::TooManyShas,
+            format!("At most {} shas may be requested at once", max),
+            Some(count.to_string()),
+        )
+    }
+
+    pub fn sha_too_long(length: usize, max: usize) -> Self {
+        Self::new(
+            Code::ShaTooLong,
+            format!("Shas may be at most {} characters", max),
+            Some(length.to_string()),
+        )
+    }
+
+    pub fn duplicate_sha(sha: &str) -> Self {
+        Self::new(
+            Code::DuplicateSha,
+            String::from("Each sha may only be requested once"),
+            Some(sha.to_string()),
+        )
+    }
+
+    pub fn unknown_dictionary_version(version: &str) -> Self {
+        Self::new(
+            Code::UnknownDictionaryVersion,
+            String::from("Unknown dictionary version"),
+            Some(version.to_string()),
+        )
+    }
+
+    pub fn invalid_query(message: &str) -> Self {
+        Self::new(Code::InvalidQuery, message.to_string(), None)
+    }
+
+    pub fn invalid_body(message: &str) -> Self {
+        Self::new(Code::InvalidBody, message.to_string(), None)
+    }
+
+    pub fn body_too_large(max: usize) -> Self {
+        Self::new(
+            Code::BodyTooLarge,
+            format!("Request bodies may be at most {} bytes", max),
+            None,
+        )
+    }
+
+    pub fn query_too_long(max: usize) -> Self {
+        Self::new(
+            Code::QueryTooLong,
+            format!("Query strings may be at most {} bytes", max),
+            None,
+        )
+    }
+
+    pub fn not_found(path: &str) -> Self {
+        Self::new(
+            Code::NotFound,
+            String::from("No route matches the path"),
+            Some(path.to_string()),
+        )
+    }
+
+    pub fn unknown_kind(kind: &str) -> Self {
+        Self::new(
+            Code::UnknownKind,
+            String::from("Unknown kind of word, expected nouns, adjectives or adverbs"),
+            Some(kind.to_string()),
+        )
+    }
+
+    pub fn unknown_word(word: &str) -> Self {
+        Self::new(
+            Code::UnknownWord,
+            String::from("The word is not in the dictionary"),
+            Some(word.to_string()),
+        )
+    }
+
+    pub fn no_free_names(count: usize) -> Self {
+        Self::new(
+            Code::NoFreeNames,
+            String::from("Not enough free names fit the constraints"),
+            Some(count.to_string()),
+        )
+    }
+
+    pub fn too_many_attempts(max: usize) -> Self {
+        Self::new(
+            Code::TooManyAttempts,
+            String::from("Finding the names took more attempts than allowed"),
+            Some(max.to_string()),
+        )
+    }
+
+    pub fn unknown_reservation(name: &str) -> Self {
+        Self::new(
+            Code::UnknownReservation,
+            String::from("The name is not reserved"),
+            Some(name.to_string()),
+        )
+    }
+
+    pub fn not_owner(name: &str) -> Self {
+        Self::new(
+            Code::NotOwner,
+            String::from("The name is reserved by another owner"),
+            Some(name.to_string()),
+        )
+    }
+
+    /// The reservation store failed. What went wrong is only logged, since
+    /// it can describe the server's files.
+    pub fn storage() -> Self {
+        Self::new(
+            Code::StorageFailed,
+            String::from("The reservation store failed"),
+            None,
+        )
+    }
+
+    pub fn unknown_event(event: Option<&str>) -> Self {
+        Self::new(
+            Code::UnknownEvent,
+            String::from("Expected a GitHub or GitLab webhook event"),
+            event.map(String::from),
+        )
+    }
+
+    pub fn invalid_signature() -> Self {
+        Self::new(
+            Code::InvalidSignature,
+            String::from("The signature does not match the secret"),
+            None,
+        )
+    }
+
+    pub fn missing_api_key() -> Self {
+        Self::new(
+            Code::MissingApiKey,
+            String::from("An API key is required"),
+            None,
+        )
+    }
+
+    pub fn invalid_api_key() -> Self {
+        Self::new(
+            Code::InvalidApiKey,
+            String::from("The API key is not valid"),
+            None,
+        )
+    }
+
+    pub fn rate_limited(retry_after: u64) -> Self {
+        Self::new(
+            Code::RateLimited,
+            format!("Too many requests, retry in {} seconds", retry_after),
+            Some(retry_after.to_string()),
+        )
+    }
+
+    /// Converts a failed lookup of the given sha into an api error.
+    pub fn lookup(sha: &str, err: ParsePhraseError) -> Self {
+        match err {
+            ParsePhraseError::Sha(_) => Self::non_hexadecimal(sha),
+            _ => Self::new(
+                Code::LookupFailed,
+                String::from("The name could not be looked up"),
+                Some(sha.to_string()),
+            ),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self.code {
+            Code::NotFound | Code::UnknownKind | Code::UnknownWord | Code::UnknownReservation => {
+                StatusCode::NOT_FOUND
+            }
+            Code::BodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Code::QueryTooLong => StatusCode::URI_TOO_LONG,
+            Code::InvalidSignature | Code::MissingApiKey | Code::InvalidApiKey => {
+                StatusCode::UNAUTHORIZED
+            }
+            Code::NotOwner => StatusCode::FORBIDDEN,
+            Code::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Code::NoFreeNames => StatusCode::CONFLICT,
+            Code::TooManyAttempts => StatusCode::UNPROCESSABLE_ENTITY,
+            Code::LookupFailed | Code::StorageFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.value {
+            Some(ref value) => write!(f, "{}: {}", self.message, value),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Fail for ApiError {}
+
+impl Schema for ApiError {
+    fn schema() -> Value {
+        let codes: Vec<Value> = CODES
+            .iter()
+            .map(|code| serde_json::to_value(code).expect("codes serialize to strings"))
+            .collect();
+        openapi::object(
+            &[
+                ("code", json!({ "type": "string", "enum": codes })),
+                ("message", openapi::string()),
+                ("value", openapi::string()),
+            ],
+            &["value"],
+        )
+    }
+}
+
+impl ResponseError for ApiError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status()).json(ErrorResponse { error: self })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use git_release_name;
+    use serde_json;
+
+    #[test]
+    fn errors_are_wrapped_in_an_envelope() {
+        let error = ApiError::unknown_format("shouty");
+        assert_eq!(
+            serde_json::to_value(ErrorResponse { error: &error }).unwrap(),
+            json!({
+                "error": {
+                    "code": "unknown_format",
+                    "message": "Unknown case format",
+                    "value": "shouty",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn bad_input_is_a_bad_request() {
+        assert_eq!(
+            ApiError::too_many_shas(2, 1).status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(ApiError::not_found("/nope").status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn oversized_requests_have_their_own_statuses() {
+        assert_eq!(
+            ApiError::body_too_large(1).status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            ApiError::query_too_long(1).status(),
+            StatusCode::URI_TOO_LONG
+        );
+        assert_eq!(
+            ApiError::sha_too_long(65, 64).status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn failed_lookups_are_converted() {
+        let err = git_release_name::lookup("xyz").unwrap_err();
+        assert_eq!(
+            ApiError::lookup("xyz", err),
+            ApiError::non_hexadecimal("xyz")
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use access;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse};
+use error::ApiError;
+use git_release_name::{self, Case};
+use limits::Limits;
+use metrics;
+use openapi::{self, Parameters};
+use output::escape;
+use param::{Format, Query, CASES};
+use serde_json::Value;
+
+/// Everything on the page is inline so it works without a network.
+const STYLE: &str = "
+body { font-family: sans-serif; max-width: 48em; margin: 2em auto; padding: 0 1em; color: #222; }
+textarea, input, select, button { font: inherit; }
+textarea { width: 100%; box-sizing: border-box; }
+table { border-collapse: collapse; margin: 1em 0; }
+td, th { text-align: left; padding: 0.25em 1em 0.25em 0; }
+.error { color: #b00; }
+footer { margin-top: 3em; font-size: small; color: #666; }
+";
+
+/// Copies the text of the button that was clicked. The clipboard api is only
+/// available to secure origins, so a selected textarea is the fallback.
+const SCRIPT: &str = "
+document.addEventListener('click', function (event) {
+  var button = event.target;
+  var text = button.getAttribute('data-copy');
+  if (text === null) return;
+  var done = function () { button.textContent = 'Copied'; };
+  if (navigator.clipboard && window.isSecureContext) {
+    navigator.clipboard.writeText(text).then(done);
+  } else {
+    var area = document.createElement('textarea');
+    area.value = text;
+    document.body.appendChild(area);
+    area.select();
+    if (document.execCommand('copy')) done();
+    document.body.removeChild(area);
+  }
+});
+";
+
+#[derive(Deserialize, Default)]
+pub struct Params {
+    shas: Option<String>,
+    format: Option<String>,
+    name: Option<String>,
+}
+
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![
+            openapi::query(
+                "shas",
+                "Shas to name, separated by whitespace or commas",
+                openapi::string(),
+            ),
+            openapi::format(),
+            openapi::query("name", "A name to find the shas of", openapi::string()),
+        ]
+    }
+}
+
+/// Splits the shas someone pasted, which may be one per line or a list.
+fn shas(text: &str) -> Vec<&str> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|sha| !sha.is_empty())
+        .collect()
+}
+
+fn error(err: &ApiError) -> String {
+    format!("<p class=\"error\">{}</p>\n", escape(&err.to_string()))
+}
+
+fn copy_button(text: &str, label: &str) -> String {
+    format!(
+        "<button type=\"button\" data-copy=\"{}\">{}</button>",
+        escape(text),
+        label
+    )
+}
+
+fn case_options(selected: &str) -> String {
+    CASES
+        .iter()
+        .map(|case| {
+            format!(
+                "<option value=\"{0}\"{1}>{0}</option>",
+                case,
+                if *case == selected { " selected" } else { "" }
+            )
+        })
+        .collect()
+}
+
+/// The names of the shas as a table.
+fn names(shas: &[String], case: Case) -> String {
+    let mut named = Vec::new();
+    let mut out = String::from("<table>\n<tr><th>sha</th><th>name</th><th></th></tr>\n");
+    for sha in shas {
+        match metrics::lookup(sha) {
+            Ok(phrase) => {
+                let name = phrase.with_case(case).to_string();
+                out.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td></tr>\n",
+                    escape(sha),
+                    escape(&name),
+                    copy_button(&name, "Copy")
+                ));
+                named.push(name);
+            }
+            Err(_) => out.push_str(&format!(
+                "<tr><td><code>{}</code></td><td class=\"error\">Not a sha</td><td></td></tr>\n",
+                escape(sha)
+            )),
+        }
+    }
+    out.push_str("</table>\n");
+    if named.len() > 1 {
+        out.push_str(&format!(
+            "<p>{}</p>\n",
+            copy_button(&named.join("\n"), "Copy all")
+        ));
+    }
+    out
+}
+
+/// The shas a name could have come from, each linking back to its name.
+fn reverse(name: &str) -> String {
+    let shas = git_release_name::reverse(name);
+    if shas.is_empty() {
+        return format!(
+            "<p class=\"error\">{} isn't a release name.</p>\n",
+            escape(name)
+        );
+    }
+    let items: String = shas
+        .iter()
+        .map(|sha| {
+            format!(
+                "<li><a href=\"/?shas={0}\"><code>{0}</code></a> {1}</li>\n",
+                sha,
+                copy_button(sha, "Copy")
+            )
+        })
+        .collect();
+    format!(
+        "<p>Names are formed from the first 8 characters of a sha, so any sha starting \
+         with one of these has the name {}.</p>\n<ul>\n{}</ul>\n",
+        escape(name),
+        items
+    )
+}
+
+/// Renders the page, along with the number of shas named.
+fn render(q: &Params, limits: &Limits) -> (String, usize) {
+    let shas_text = q.shas.as_deref().unwrap_or("");
+    let name = q.name.as_deref().unwrap_or("").trim();
+    let (format, case, format_error) = match Format::from_param(q.format.as_deref()) {
+        Ok(format) => (q.format.as_deref().unwrap_or("lower"), *format, None),
+        Err(err) => ("lower", Case::Lower, Some(err)),
+    };
+    let shas = limits.shas(shas(shas_text), limits.shas);
+
+    let mut body = format!(
+        "<h1>Release names</h1>\n\
+         <form method=\"get\" action=\"/\">\n\
+         <p><label for=\"shas\">Shas, one per line</label></p>\n\
+         <textarea id=\"shas\" name=\"shas\" rows=\"6\">{}</textarea>\n\
+         <p><label for=\"format\">Case</label> \
+         <select id=\"format\" name=\"format\">{}</select> \
+         <button type=\"submit\">Name</button></p>\n\
+         </form>\n",
+        escape(shas_text),
+        case_options(format)
+    );
+    let mut named = 0;
+    match (format_error, shas) {
+        (Some(err), _) | (None, Err(err)) => body.push_str(&error(&err)),
+        (None, Ok(ref shas)) if !shas.is_empty() => {
+            body.push_str(&names(shas, case));
+            named = shas.len();
+        }
+        (None, Ok(_)) => {}
+    }
+
+    body.push_str(&format!(
+        "<h2>Find a sha</h2>\n\
+         <form method=\"get\" action=\"/\">\n\
+         <p><input name=\"name\" value=\"{}\" size=\"40\" \
+         placeholder=\"intentionally mirky swineherds\" aria-label=\"Release name\"> \
+         <button type=\"submit\">Find</button></p>\n\
+         </form>\n",
+        escape(name)
+    ));
+    if !name.is_empty() {
+        body.push_str(&reverse(name));
+    }
+
+    let page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>Release names</title>\n<style>{}</style>\n</head>\n<body>\n{}\
+         <footer>Names are also available from the <a href=\"/api/openapi.json\">api</a>.\
+         </footer>\n<script>{}</script>\n</body>\n</html>\n",
+        STYLE, body, SCRIPT
+    );
+    (page, named)
+}
+
+pub fn handler(mut req: HttpRequest, q: Query<Params>) -> HttpResponse {
+    let (page, shas) = render(&q, &Limits::of(&req));
+    access::shas(&mut req, shas);
+    HttpResponse::Ok()
+        .header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/html; charset=utf-8"),
+        )
+        .body(page)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn page(shas: Option<&str>, format: Option<&str>, name: Option<&str>) -> String {
+        render(
+            &Params {
+                shas: shas.map(String::from),
+                format: format.map(String::from),
+                name: name.map(String::from),
+            },
+            &Limits::default(),
+        )
+        .0
+    }
+
+    #[test]
+    fn shas_are_split_on_lines_and_commas() {
+        assert_eq!(shas("abc, 123\n\n  def\r\n"), vec!["abc", "123", "def"]);
+    }
+
+    #[test]
+    fn the_form_is_empty_at_first() {
+        let html = page(None, None, None);
+        assert!(html.contains("<option value=\"lower\" selected>"));
+        assert!(!html.contains("<table>"));
+        assert!(!html.contains("src=") && !html.contains("stylesheet"));
+    }
+
+    #[test]
+    fn shas_are_named_in_the_chosen_case() {
+        let html = page(Some("abc123\nxyz"), Some("kebab"), None);
+        assert!(html.contains("<td>proximally-kilted-rancor</td>"));
+        assert!(html.contains("data-copy=\"proximally-kilted-rancor\""));
+        assert!(html.contains("<code>xyz</code></td><td class=\"error\">Not a sha"));
+        assert!(html.contains("<option value=\"kebab\" selected>"));
+    }
+
+    #[test]
+    fn names_can_be_reversed() {
+        let html = page(None, None, Some("Intentionally-Mirky-Swineherds"));
+        assert!(html.contains("<a href=\"/?shas=a9677113\">"));
+        let html = page(None, None, Some("<b>nothing</b>"));
+        assert!(html.contains("&lt;b&gt;nothing&lt;/b&gt; isn't a release name"));
+    }
+
+    #[test]
+    fn unknown_cases_are_reported() {
+        let (html, named) = render(
+            &Params {
+                shas: Some(String::from("abc123")),
+                format: Some(String::from("shouty")),
+                name: None,
+            },
+            &Limits::default(),
+        );
+        assert_eq!(named, 0);
+        assert!(html.contains("<p class=\"error\">Unknown case format: shouty</p>"));
+        assert!(!html.contains("<table>"));
+    }
+}
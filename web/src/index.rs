@@ -1,29 +1,52 @@
 use super::Response;
-use actix_web::{Json, Query};
-use git_release_name::{self, Case};
-use param;
-use std::collections::HashMap;
+use access;
+use actix_web::{HttpRequest, HttpResponse};
+use cache::Etag;
+use error::ApiError;
+use git_release_name::Case;
+use limits::Limits;
+use metrics;
+use openapi::{self, Parameters, Schema};
+use output::{Output, Row};
+use param::{self, Format, Query};
+use serde_json::Value;
+use std::collections::BTreeMap;
 
 #[derive(Deserialize)]
 pub struct Params {
     shas: param::CSV,
-    format: Option<param::Format>,
+    format: Option<String>,
+    version: Option<String>,
+    output: Option<String>,
 }
 
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![
+            openapi::required_query("shas", "Comma separated shas", param::CSV::schema()),
+            openapi::format(),
+            openapi::version(),
+            openapi::output(),
+        ]
+    }
+}
+
+/// The names by sha. They are sorted by sha so the same request always gets
+/// the same bytes, as a strong ETag promises.
 #[derive(Serialize, Debug, Eq, PartialEq)]
 pub struct BulkNames {
-    names: HashMap<String, Option<String>>,
+    names: BTreeMap<String, Option<String>>,
 }
 
 impl BulkNames {
-    fn new(names: HashMap<String, Option<String>>) -> Self {
+    fn new(names: BTreeMap<String, Option<String>>) -> Self {
         Self { names }
     }
 
     fn from_list(case: Case, shas: &[String]) -> Self {
-        let mut map = HashMap::new();
+        let mut map = BTreeMap::new();
         for sha in shas {
-            let name = git_release_name::lookup(&sha)
+            let name = metrics::lookup(sha)
                 .map(|name| name.with_case(case).to_string())
                 .ok();
             map.insert(sha.to_string(), name);
@@ -32,15 +55,50 @@ impl BulkNames {
     }
 }
 
-pub fn handler(q: Query<Params>) -> Json<Response<BulkNames>> {
-    let format = q.format.unwrap_or(Case::Lower.into());
+impl Schema for BulkNames {
+    fn schema() -> Value {
+        openapi::object(
+            &[(
+                "names",
+                json!({
+                    "type": "object",
+                    "description": "The name of each sha, or null when it can't be named",
+                    "additionalProperties": openapi::nullable(openapi::string()),
+                }),
+            )],
+            &[],
+        )
+    }
+}
+
+pub fn handler(mut req: HttpRequest, q: Query<Params>) -> Result<HttpResponse, ApiError> {
+    let format = Format::from_param(q.format.as_deref())?;
+    param::version(q.version.as_deref())?;
+    let output = Output::negotiate(&req, q.output.as_deref(), Output::Json)?;
+    let limits = Limits::of(&req);
+    let shas = limits.shas(q.shas.iter().map(String::as_str), limits.shas)?;
+
+    metrics::bulk_shas("index", shas.len());
+    access::shas(&mut req, shas.len());
 
-    Json(Response::new(BulkNames::from_list(*format, &q.shas)))
+    let bulk_names = BulkNames::from_list(*format, &shas);
+    let etag = Etag::new(&shas, *format, output);
+    if etag.matches(&req) {
+        return Ok(etag.not_modified());
+    }
+
+    let rows: Vec<Row> = shas
+        .iter()
+        .map(|sha| Row::new(sha, bulk_names.names[sha].as_deref()))
+        .collect();
+    Ok(etag.apply(output.respond(&Response::new(&bulk_names), &rows)))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use actix_web::http::Method;
+    use testing::{fetch, server};
 
     #[test]
     fn bulk_names_can_be_formed_from_list() {
@@ -61,4 +119,16 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn the_same_shas_always_get_the_same_bytes() {
+        let mut srv = server();
+        let url = "/api/release-name?shas=fff,abc,123,def,a96,xyz,777";
+        let (status, first) = fetch(&mut srv, Method::GET, url, "");
+        assert_eq!(status, 200);
+        assert!(first.find("\"123\"") < first.find("\"abc\""), "{}", first);
+        for _ in 0..10 {
+            assert_eq!(fetch(&mut srv, Method::GET, url, "").1, first);
+        }
+    }
 }
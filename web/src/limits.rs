@@ -0,0 +1,218 @@
+use actix_web::http::header;
+use actix_web::middleware::{Middleware, Started};
+use actix_web::{HttpMessage, HttpRequest, Result};
+use error::ApiError;
+use std::collections::HashSet;
+use std::str::FromStr;
+use v2;
+
+/// What happens to a sha that is requested more than once.
+#[derive(Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Duplicates {
+    /// Every copy is named, so responses line up with the request
+    Keep,
+    /// Only the first copy is named
+    Merge,
+    /// The request is rejected
+    Reject,
+}
+
+impl FromStr for Duplicates {
+    type Err = ();
+
+    fn from_str(duplicates: &str) -> Result<Duplicates, ()> {
+        match duplicates {
+            "keep" => Ok(Duplicates::Keep),
+            "merge" => Ok(Duplicates::Merge),
+            "reject" => Ok(Duplicates::Reject),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How much a single request may ask for. As a middleware the limits turn
+/// away oversized requests before they are read, and are handed to the
+/// routes that check lists of shas.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Limits {
+    /// The most shas in a query string
+    pub shas: usize,
+    /// The most shas in a request body
+    pub bulk_shas: usize,
+    /// The longest a single sha may be, in bytes
+    pub sha_length: usize,
+    /// The largest request body, in bytes
+    pub body: usize,
+    /// The longest query string, in bytes
+    pub query: usize,
+    pub duplicates: Duplicates,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            shas: 1000,
+            bulk_shas: 10_000,
+            sha_length: 64,
+            body: 1_048_576,
+            query: 65_536,
+            duplicates: Duplicates::Keep,
+        }
+    }
+}
+
+impl Limits {
+    /// The limits of the app handling a request.
+    pub fn of<S>(req: &HttpRequest<S>) -> Limits {
+        req.extensions()
+            .get::<Limits>()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Trims the requested shas and drops blank ones, then checks them
+    /// against the limits and rejects any that aren't ascii. Duplicates are
+    /// kept, merged or rejected by the policy.
+    pub fn shas<'a, I>(&self, shas: I, max: usize) -> Result<Vec<String>, ApiError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut seen = HashSet::new();
+        let mut checked = Vec::new();
+        for sha in shas
+            .into_iter()
+            .map(str::trim)
+            .filter(|sha| !sha.is_empty())
+        {
+            if sha.len() > self.sha_length {
+                return Err(ApiError::sha_too_long(sha.len(), self.sha_length));
+            }
+            // Other shas that aren't hexadecimal are reported one by one by
+            // the routes, but no character outside of ascii can be.
+            if !sha.is_ascii() {
+                return Err(ApiError::non_hexadecimal(sha));
+            }
+            if !seen.insert(sha) {
+                match self.duplicates {
+                    Duplicates::Keep => {}
+                    Duplicates::Merge => continue,
+                    Duplicates::Reject => return Err(ApiError::duplicate_sha(sha)),
+                }
+            }
+            checked.push(sha.to_string());
+        }
+        if checked.len() > max {
+            return Err(ApiError::too_many_shas(checked.len(), max));
+        }
+        Ok(checked)
+    }
+
+    /// Checks the parts of a request that are known before it is read.
+    fn check<S>(&self, req: &HttpRequest<S>) -> Result<(), ApiError> {
+        if req.query_string().len() > self.query {
+            return Err(ApiError::query_too_long(self.query));
+        }
+        let length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse::<usize>().ok());
+        match length {
+            Some(length) if length > self.body => Err(ApiError::body_too_large(self.body)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<S> Middleware<S> for Limits {
+    fn start(&self, req: &mut HttpRequest<S>) -> Result<Started> {
+        if let Err(err) = self.check(req) {
+            return Ok(Started::Response(v2::error_response(req, &err)));
+        }
+        req.extensions_mut().insert(*self);
+        Ok(Started::Done)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn limits(duplicates: Duplicates) -> Limits {
+        Limits {
+            duplicates,
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn shas_are_trimmed() {
+        let shas = limits(Duplicates::Keep).shas(vec![" abc ", "", "\t123\n", "  "], 10);
+        assert_eq!(shas, Ok(vec![String::from("abc"), String::from("123")]));
+    }
+
+    #[test]
+    fn duplicates_follow_the_policy() {
+        let shas = vec!["abc", "123", " abc"];
+        assert_eq!(
+            limits(Duplicates::Keep)
+                .shas(shas.clone(), 10)
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            limits(Duplicates::Merge).shas(shas.clone(), 10),
+            Ok(vec![String::from("abc"), String::from("123")])
+        );
+        assert_eq!(
+            limits(Duplicates::Reject).shas(shas, 10),
+            Err(ApiError::duplicate_sha("abc"))
+        );
+    }
+
+    #[test]
+    fn shas_are_counted_after_merging() {
+        let merge = limits(Duplicates::Merge);
+        assert!(merge.shas(vec!["abc", "abc", "123"], 2).is_ok());
+        assert_eq!(
+            merge.shas(vec!["abc", "def", "123"], 2),
+            Err(ApiError::too_many_shas(3, 2))
+        );
+    }
+
+    #[test]
+    fn long_shas_are_rejected() {
+        let sha = "a".repeat(65);
+        assert_eq!(
+            Limits::default().shas(vec![sha.as_str()], 10),
+            Err(ApiError::sha_too_long(65, 64))
+        );
+    }
+
+    #[test]
+    fn shas_outside_of_ascii_are_rejected() {
+        assert_eq!(
+            Limits::default().shas(vec!["abc", "abcdefg\u{e9}"], 10),
+            Err(ApiError::non_hexadecimal("abcdefg\u{e9}"))
+        );
+        assert!(Limits::default().shas(vec!["abc", "xyz"], 10).is_ok());
+    }
+
+    #[test]
+    fn oversized_requests_are_turned_away() {
+        let limits = Limits {
+            query: 8,
+            body: 16,
+            ..Limits::default()
+        };
+        let req = TestRequest::with_uri("/api/release-name?shas=abc,123").finish();
+        assert_eq!(limits.check(&req), Err(ApiError::query_too_long(8)));
+        let req = TestRequest::with_header("Content-Length", "17").finish();
+        assert_eq!(limits.check(&req), Err(ApiError::body_too_large(16)));
+        let req = TestRequest::with_header("Content-Length", "16").finish();
+        assert_eq!(limits.check(&req), Ok(()));
+    }
+}
@@ -1,18 +1,75 @@
+extern crate actix;
 extern crate actix_web;
+extern crate clap;
+extern crate failure;
+extern crate futures;
 extern crate git_release_name;
+extern crate hmac;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate rand;
+extern crate rusqlite;
+#[macro_use]
 extern crate serde_json;
+extern crate serde_urlencoded;
+extern crate sha2;
+extern crate tokio_core;
+extern crate tokio_uds;
+extern crate toml;
 
-use actix_web::{http, server, App};
+use actix::{Addr, Syn, SystemRunner};
+use actix_web::server::{HttpServer, IntoHttpHandler};
+use actix_web::{http, server, App, HttpRequest, HttpResponse};
 use serde::Serialize;
+use std::fs;
+use std::io;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process;
 
+mod access;
+mod allocate;
+mod auth;
+mod badge;
+mod bulk;
+mod cache;
+mod config;
+mod dictionary;
+mod error;
+mod home;
 mod index;
+mod limits;
+mod metrics;
+mod openapi;
+mod output;
 mod param;
 mod random;
+mod ratelimit;
+mod reservation;
 mod show;
+mod signature;
+mod slash;
+mod status;
+#[cfg(test)]
+mod testing;
+mod timeout;
+mod v2;
+mod webhook;
+
+use access::AccessLog;
+use auth::{Auth, Keys};
+use config::Config;
+use error::ApiError;
+use limits::Limits;
+use openapi::Schema;
+use ratelimit::RateLimiter;
+use reservation::Store;
+use serde_json::Value;
+use slash::Slash;
+use timeout::RequestTimeout;
+use webhook::Webhook;
 
 #[derive(Serialize)]
 pub struct Response<T>
@@ -31,19 +88,356 @@ where
     }
 }
 
+impl<T> Schema for Response<T>
+where
+    T: Serialize + Schema,
+{
+    fn schema() -> Value {
+        openapi::object(&[("data", T::schema())], &[])
+    }
+}
+
+/// A name and the sha it was formed from.
+#[derive(Serialize)]
+pub struct Name {
+    name: String,
+    sha: String,
+}
+
+impl Name {
+    pub fn new(name: String, sha: String) -> Self {
+        Self { name, sha }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sha(&self) -> &str {
+        &self.sha
+    }
+}
+
+impl Schema for Name {
+    fn schema() -> Value {
+        openapi::object(
+            &[("name", openapi::string()), ("sha", openapi::string())],
+            &[],
+        )
+    }
+}
+
+/// The parts of the server that are built from its configuration, shared by
+/// every worker. Reservations, webhooks and slash commands are only served
+/// when they are configured.
+#[derive(Clone)]
+struct Services {
+    limits: Limits,
+    auth: Auth,
+    rates: RateLimiter,
+    reservations: Option<Addr<Syn, Store>>,
+    webhook: Option<Webhook>,
+    slash: Option<Slash>,
+}
+
+fn app(log: AccessLog, services: Services) -> App {
+    let max_body = services.limits.body;
+    let app = App::new()
+        .middleware(log)
+        .middleware(metrics::Metrics)
+        .middleware(services.limits)
+        .middleware(services.auth)
+        .middleware(services.rates)
+        .resource(openapi::path("home"), |r| {
+            r.name("home");
+            r.method(http::Method::GET).with2(home::handler)
+        })
+        .resource(openapi::path("metrics"), |r| {
+            r.name("metrics");
+            r.method(http::Method::GET).f(metrics::handler)
+        })
+        .resource(openapi::path("healthz"), |r| {
+            r.name("healthz");
+            r.method(http::Method::GET).f(status::healthz)
+        })
+        .resource(openapi::path("readyz"), |r| {
+            r.name("readyz");
+            r.method(http::Method::GET).f(status::readyz)
+        })
+        .resource(openapi::path("version"), |r| {
+            r.name("version");
+            r.method(http::Method::GET).f(status::version)
+        })
+        .resource(openapi::path("openapi"), |r| {
+            r.name("openapi");
+            r.method(http::Method::GET).f(openapi::handler)
+        })
+        .resource(openapi::path("dictionary"), |r| {
+            r.name("dictionary");
+            r.method(http::Method::GET).with2(dictionary::index)
+        })
+        .resource(openapi::path("word"), |r| {
+            r.name("word");
+            r.method(http::Method::GET).with(dictionary::show)
+        })
+        .resource(openapi::path("index"), move |r| {
+            r.name("index");
+            r.method(http::Method::GET).with2(index::handler);
+            r.method(http::Method::POST)
+                .with3(bulk::handler)
+                .2
+                .limit(max_body)
+                .error_handler(bulk::body_error);
+        })
+        .resource(openapi::path("allocate"), move |r| {
+            r.name("allocate");
+            r.method(http::Method::POST)
+                .with3(allocate::handler)
+                .2
+                .limit(max_body)
+                .error_handler(bulk::body_error);
+        })
+        .resource(openapi::path("random"), |r| {
+            r.name("random");
+            r.method(http::Method::GET).with2(random::handler)
+        })
+        .resource(openapi::path("badge"), |r| {
+            r.name("badge");
+            r.method(http::Method::GET).with3(badge::handler)
+        })
+        .resource(openapi::path("show"), |r| {
+            r.name("show");
+            r.method(http::Method::GET).with3(show::handler)
+        })
+        .resource(openapi::path("v2_names"), move |r| {
+            r.name("v2_names");
+            r.method(http::Method::GET).f(v2::index);
+            r.method(http::Method::POST)
+                .with2(v2::bulk)
+                .1
+                .limit(max_body)
+                .error_handler(v2::body_error);
+        })
+        .resource(openapi::path("v2_name"), |r| {
+            r.name("v2_name");
+            r.method(http::Method::GET).f(v2::show)
+        })
+        .resource(openapi::path("v2_random"), |r| {
+            r.name("v2_random");
+            r.method(http::Method::GET).f(v2::random)
+        });
+    let app = match services.reservations {
+        Some(store) => reservation::resources(app, store, max_body),
+        None => app,
+    };
+    let app = match services.webhook {
+        Some(hook) => app.resource(openapi::path("webhook"), move |r| {
+            r.name("webhook");
+            r.method(http::Method::POST)
+                .a(move |req| webhook::handler(&hook, req))
+        }),
+        None => app,
+    };
+    let app = match services.slash {
+        Some(slash) => app.resource(openapi::path("slash"), move |r| {
+            r.name("slash");
+            r.method(http::Method::POST)
+                .a(move |req| slash::handler(&slash, req))
+        }),
+        None => app,
+    };
+    app.default_resource(|r| r.f(not_found))
+}
+
+fn not_found(req: HttpRequest) -> HttpResponse {
+    v2::error_response(&req, &ApiError::not_found(req.path()))
+}
+
 fn main() {
-    server::new(|| {
-        App::new()
-            .route("/api/release-name", http::Method::GET, index::handler)
-            .route(
-                "/api/release-name/random",
-                http::Method::GET,
-                random::handler,
-            )
-            .resource("/api/release-name/{sha}", |r| {
-                r.method(http::Method::GET).with2(show::handler)
-            })
-    }).bind("0.0.0.0:6767")
-        .unwrap()
-        .run();
+    let config = Config::load().unwrap_or_else(|err| {
+        eprintln!("Invalid configuration: {}", err);
+        process::exit(1);
+    });
+
+    // The reservation store runs in the system the server runs in.
+    let sys = actix::System::new("git-release-name-web");
+    let reservations = config.reservations.as_ref().map(|path| {
+        Store::start(path).unwrap_or_else(|err| {
+            eprintln!("Unable to open reservations: {}: {}", path.display(), err);
+            process::exit(1);
+        })
+    });
+
+    let keys =
+        Keys::load(config.api_keys_file.as_deref(), &config.api_keys).unwrap_or_else(|err| {
+            eprintln!("Unable to load API keys: {}", err);
+            process::exit(1);
+        });
+    if config.require_api_key && keys.is_empty() {
+        eprintln!("API keys are required but none are configured");
+        process::exit(1);
+    }
+
+    let services = Services {
+        limits: config.limits(),
+        auth: Auth::new(
+            keys,
+            config.require_api_key,
+            config.public_routes.clone(),
+            config.trusted_proxies.clone(),
+        ),
+        rates: RateLimiter::new(config.rate_limits.clone()),
+        reservations,
+        webhook: config
+            .webhook_secret
+            .clone()
+            .map(|secret| Webhook::new(secret, config.webhook_forward.clone())),
+        slash: config.slash_secret.clone().map(Slash::new),
+    };
+
+    let result = match config.socket {
+        Some(ref path) => run_unix(&config, path, services, sys),
+        None => run_tcp(&config, services, sys),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Unable to start server: {}", err);
+        process::exit(1);
+    }
+}
+
+fn configure<H>(mut server: HttpServer<H>, config: &Config) -> HttpServer<H>
+where
+    H: IntoHttpHandler + 'static,
+{
+    if let Some(workers) = config.workers {
+        server = server.workers(workers);
+    }
+    if let Some(keep_alive) = config.keep_alive {
+        server = server.keep_alive(if keep_alive == 0 {
+            server::KeepAlive::Disabled
+        } else {
+            server::KeepAlive::Timeout(keep_alive)
+        });
+    }
+    if let Some(timeout) = config.shutdown_timeout {
+        server = server.shutdown_timeout(timeout);
+    }
+    server
+}
+
+fn run_tcp(config: &Config, services: Services, sys: SystemRunner) -> io::Result<()> {
+    let log = AccessLog::new(config.log_format, config.log_level);
+    let timeout = config.request_timeout();
+    let addr = config.addr();
+    let server = configure(
+        server::new(move || RequestTimeout::new(app(log, services.clone()), timeout)),
+        config,
+    )
+    .bind(&addr)
+    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", addr, e)))?;
+    server.start();
+    println!("Listening on http://{}", addr);
+    sys.run();
+    Ok(())
+}
+
+fn run_unix(config: &Config, path: &Path, services: Services, sys: SystemRunner) -> io::Result<()> {
+    // A socket left behind by a previous run would make binding fail, but
+    // one that still accepts connections belongs to a running server.
+    if let Ok(meta) = fs::symlink_metadata(path) {
+        if meta.file_type().is_socket() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!(
+                        "{}: another server is listening on this socket",
+                        path.display()
+                    ),
+                ));
+            }
+            fs::remove_file(path)?;
+        }
+    }
+
+    let log = AccessLog::new(config.log_format, config.log_level);
+    let timeout = config.request_timeout();
+    let listener = tokio_uds::UnixListener::bind(path, actix::Arbiter::handle())
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    configure(
+        server::new(move || RequestTimeout::new(app(log, services.clone()), timeout)),
+        config,
+    )
+    .start_incoming(listener.incoming(), false);
+    println!("Listening on unix:{}", path.display());
+    sys.run();
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::http::Method;
+    use serde_json::{self, Value};
+    use testing::{send, server, SECRET};
+
+    /// A sha whose 8th byte falls inside a multi-byte character.
+    const SHA: &str = "abcdefg\u{e9}";
+
+    #[test]
+    fn multi_byte_shas_are_rejected_by_every_route() {
+        let mut srv = server();
+        let json = r#"{"shas": ["abcdefg\u00e9"]}"#;
+        let push = include_str!("../tests/fixtures/gitlab_push.json")
+            .replace("b6568db1bc1dcd7f8b4d5a946b0b91f9dacd7327", SHA);
+        let requests = vec![
+            (Method::GET, "/api/release-name/abcdefg%C3%A9", ""),
+            (Method::GET, "/api/release-name/abcdefg%C3%A9/badge.svg", ""),
+            (Method::GET, "/api/release-name?shas=abcdefg%C3%A9", ""),
+            (Method::POST, "/api/release-name", json),
+            (Method::GET, "/api/v2/names/abcdefg%C3%A9", ""),
+            (Method::GET, "/api/v2/names?shas=abcdefg%C3%A9", ""),
+            (Method::POST, "/api/v2/names", json),
+            (Method::POST, "/api/webhook", &push),
+        ];
+        for (method, path, body) in requests {
+            let req = srv
+                .client(method.clone(), path)
+                .header("X-Gitlab-Event", "Push Hook")
+                .header("X-Gitlab-Token", SECRET)
+                .content_type("application/json")
+                .body(body.to_string())
+                .unwrap();
+            let (status, _, body) = send(&mut srv, req);
+            assert_eq!(status, 400, "{} {}: {}", method, path, body);
+            let body: Value = serde_json::from_str(&body).unwrap();
+            let error = match body["errors"][0] {
+                Value::Null => &body["error"],
+                ref error => error,
+            };
+            assert_eq!(error["code"], "non_hexadecimal", "{} {}", method, path);
+            assert_eq!(error["value"], SHA, "{} {}", method, path);
+        }
+
+        let req = srv
+            .client(Method::GET, "/?shas=abcdefg%C3%A9")
+            .finish()
+            .unwrap();
+        let (status, _, body) = send(&mut srv, req);
+        assert_eq!(status, 200);
+        assert!(body.contains("hexadecimal"), "{}", body);
+
+        let req = srv
+            .client(Method::POST, "/api/slash")
+            .header("Authorization", format!("Token {}", SECRET))
+            .content_type("application/x-www-form-urlencoded")
+            .body("command=%2Frn&text=abcdefg%C3%A9")
+            .unwrap();
+        let (status, _, body) = send(&mut srv, req);
+        assert_eq!(status, 200);
+        assert!(body.contains("hexadecimal"), "{}", body);
+
+        // The workers are still there to answer.
+        let req = srv.client(Method::GET, "/healthz").finish().unwrap();
+        assert_eq!(send(&mut srv, req).0, 200);
+    }
 }
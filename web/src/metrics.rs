@@ -0,0 +1,271 @@
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::{Finished, Middleware, Started};
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, Result};
+use git_release_name::{self, ParsePhraseError, Phrase};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Upper bounds, in seconds, of the request latency buckets.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0,
+];
+
+/// Upper bounds of the shas per bulk request buckets.
+const SHAS_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0, 5000.0, 10000.0];
+
+/// Every metric collected by the server, shared by all of the workers.
+static METRICS: Mutex<Registry> = Mutex::new(Registry::new());
+
+/// The route and method of a request.
+type Labels = (String, String);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(i) = self.bounds.iter().position(|&bound| value <= bound) {
+            self.counts[i] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let labels = labels.trim_end_matches(',');
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug)]
+struct Registry {
+    requests: BTreeMap<(String, String, u16), u64>,
+    errors: BTreeMap<(String, String, u16), u64>,
+    latency: BTreeMap<Labels, Histogram>,
+    bulk_shas: BTreeMap<&'static str, Histogram>,
+    lookup_failures: BTreeMap<&'static str, u64>,
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Registry {
+            requests: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            latency: BTreeMap::new(),
+            bulk_shas: BTreeMap::new(),
+            lookup_failures: BTreeMap::new(),
+        }
+    }
+
+    fn request(&mut self, labels: Labels, status: StatusCode, seconds: f64) {
+        let key = (labels.0.clone(), labels.1.clone(), status.as_u16());
+        if status.is_client_error() || status.is_server_error() {
+            *self.errors.entry(key.clone()).or_insert(0) += 1;
+        }
+        *self.requests.entry(key).or_insert(0) += 1;
+        self.latency
+            .entry(labels)
+            .or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+            .observe(seconds);
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP release_name_http_requests_total Requests handled by route.\n");
+        out.push_str("# TYPE release_name_http_requests_total counter\n");
+        for (&(ref route, ref method, status), count) in &self.requests {
+            let _ = writeln!(
+                out,
+                "release_name_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}",
+                route, method, status, count
+            );
+        }
+
+        out.push_str("# HELP release_name_http_errors_total Requests answered with an error status by route.\n");
+        out.push_str("# TYPE release_name_http_errors_total counter\n");
+        for (&(ref route, ref method, status), count) in &self.errors {
+            let _ = writeln!(
+                out,
+                "release_name_http_errors_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}",
+                route, method, status, count
+            );
+        }
+
+        out.push_str("# HELP release_name_http_request_duration_seconds Time spent handling requests by route.\n");
+        out.push_str("# TYPE release_name_http_request_duration_seconds histogram\n");
+        for ((route, method), histogram) in &self.latency {
+            let labels = format!("route=\"{}\",method=\"{}\",", route, method);
+            histogram.render(
+                &mut out,
+                "release_name_http_request_duration_seconds",
+                &labels,
+            );
+        }
+
+        out.push_str("# HELP release_name_bulk_shas Shas named per bulk request.\n");
+        out.push_str("# TYPE release_name_bulk_shas histogram\n");
+        for (route, histogram) in &self.bulk_shas {
+            let labels = format!("route=\"{}\",", route);
+            histogram.render(&mut out, "release_name_bulk_shas", &labels);
+        }
+
+        out.push_str(
+            "# HELP release_name_lookup_failures_total Shas that could not be named by reason.\n",
+        );
+        out.push_str("# TYPE release_name_lookup_failures_total counter\n");
+        for (reason, count) in &self.lookup_failures {
+            let _ = writeln!(
+                out,
+                "release_name_lookup_failures_total{{reason=\"{}\"}} {}",
+                reason, count
+            );
+        }
+
+        out
+    }
+}
+
+fn registry() -> ::std::sync::MutexGuard<'static, Registry> {
+    // A panic while recording can't leave the counters in a state worth
+    // refusing to serve.
+    METRICS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Records how many shas were asked for by a bulk route.
+pub fn bulk_shas(route: &'static str, count: usize) {
+    registry()
+        .bulk_shas
+        .entry(route)
+        .or_insert_with(|| Histogram::new(SHAS_BUCKETS))
+        .observe(count as f64);
+}
+
+/// Looks up the name of a sha, counting failures by their reason.
+pub fn lookup(sha: &str) -> Result<Phrase, ParsePhraseError> {
+    git_release_name::lookup(sha).inspect_err(|err| {
+        *registry().lookup_failures.entry(reason(err)).or_insert(0) += 1;
+    })
+}
+
+fn reason(err: &ParsePhraseError) -> &'static str {
+    match *err {
+        ParsePhraseError::Sha(_) => "non_hexadecimal",
+        ParsePhraseError::WordNotFound => "word_not_found",
+        ParsePhraseError::Layout(_) => "invalid_layout",
+        _ => "other",
+    }
+}
+
+/// Times every request and counts it against the name of its resource.
+pub struct Metrics;
+
+struct Start(Instant);
+
+impl<S> Middleware<S> for Metrics {
+    fn start(&self, req: &mut HttpRequest<S>) -> Result<Started> {
+        req.extensions_mut().insert(Start(Instant::now()));
+        Ok(Started::Done)
+    }
+
+    fn finish(&self, req: &mut HttpRequest<S>, resp: &HttpResponse) -> Finished {
+        if let Some(&Start(start)) = req.extensions().get::<Start>() {
+            let route = match req.resource().name() {
+                "" => "unmatched",
+                name => name,
+            };
+            let labels = (route.to_string(), req.method().to_string());
+            registry().request(labels, resp.status(), start.elapsed().as_secs_f64());
+        }
+        Finished::Done
+    }
+}
+
+/// Serves the metrics in the Prometheus text format.
+pub fn handler(_: HttpRequest) -> HttpResponse {
+    HttpResponse::build(StatusCode::OK)
+        .header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8"),
+        )
+        .body(registry().render())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn histograms_are_cumulative() {
+        let mut histogram = Histogram::new(&[1.0, 10.0]);
+        histogram.observe(1.0);
+        histogram.observe(3.0);
+        histogram.observe(30.0);
+        let mut out = String::new();
+        histogram.render(&mut out, "shas", "route=\"bulk\",");
+        assert_eq!(
+            out,
+            "shas_bucket{route=\"bulk\",le=\"1\"} 1\n\
+             shas_bucket{route=\"bulk\",le=\"10\"} 2\n\
+             shas_bucket{route=\"bulk\",le=\"+Inf\"} 3\n\
+             shas_sum{route=\"bulk\"} 34\n\
+             shas_count{route=\"bulk\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn requests_are_counted_by_route() {
+        let mut registry = Registry::new();
+        let show = (String::from("show"), String::from("GET"));
+        registry.request(show.clone(), StatusCode::OK, 0.001);
+        registry.request(show.clone(), StatusCode::OK, 0.002);
+        registry.request(show, StatusCode::BAD_REQUEST, 0.001);
+        let out = registry.render();
+        assert!(out.contains(
+            "release_name_http_requests_total{route=\"show\",method=\"GET\",status=\"200\"} 2\n"
+        ));
+        assert!(out.contains(
+            "release_name_http_errors_total{route=\"show\",method=\"GET\",status=\"400\"} 1\n"
+        ));
+        assert!(out.contains(
+            "release_name_http_request_duration_seconds_count{route=\"show\",method=\"GET\"} 3\n"
+        ));
+    }
+
+    #[test]
+    fn lookup_failures_have_reasons() {
+        let err = git_release_name::lookup("xyz").unwrap_err();
+        assert_eq!(reason(&err), "non_hexadecimal");
+    }
+}
@@ -0,0 +1,1006 @@
+use actix_web::http::Method;
+use actix_web::{HttpRequest, Json};
+use allocate;
+use badge;
+use bulk;
+use dictionary::{self, Page, Word};
+use error::ApiError;
+use git_release_name::DICTIONARY_VERSION;
+use home;
+use index::{self, BulkNames};
+use output::Output;
+use param::Format;
+use random;
+use reservation::{self, Reservation};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use show;
+use slash;
+use status::Version;
+use v2::{self, Names};
+use webhook::{self, Summary};
+use {Name, Response};
+
+/// A type that describes itself with an OpenAPI schema.
+pub trait Schema {
+    fn schema() -> Value;
+}
+
+/// Query parameters that describe their fields as OpenAPI parameters.
+pub trait Parameters {
+    fn parameters() -> Vec<Value>;
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema() -> Value {
+        array(T::schema())
+    }
+}
+
+/// Routes without query parameters.
+#[derive(Deserialize)]
+pub struct NoParams {}
+
+impl Parameters for NoParams {
+    fn parameters() -> Vec<Value> {
+        Vec::new()
+    }
+}
+
+pub fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+pub fn integer() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+pub fn nullable(mut schema: Value) -> Value {
+    schema["nullable"] = Value::Bool(true);
+    schema
+}
+
+pub fn string_enum(values: &[&str]) -> Value {
+    json!({ "type": "string", "enum": values })
+}
+
+pub fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+/// An object with the given properties, all of them required unless they
+/// are listed as optional.
+pub fn object(properties: &[(&str, Value)], optional: &[&str]) -> Value {
+    let required: Vec<&str> = properties
+        .iter()
+        .map(|&(name, _)| name)
+        .filter(|name| !optional.contains(name))
+        .collect();
+    let properties: Map<String, Value> = properties
+        .iter()
+        .map(|&(name, ref schema)| (name.to_string(), schema.clone()))
+        .collect();
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    schema
+}
+
+/// An optional query parameter.
+pub fn query(name: &str, description: &str, schema: Value) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": false,
+        "description": description,
+        "schema": schema,
+    })
+}
+
+/// A query parameter that must be given.
+pub fn required_query(name: &str, description: &str, schema: Value) -> Value {
+    let mut param = query(name, description, schema);
+    param["required"] = Value::Bool(true);
+    param
+}
+
+/// The `format` parameter accepted by every naming route.
+pub fn format() -> Value {
+    query("format", "The case of the names", Format::schema())
+}
+
+/// The `version` parameter accepted by every naming route.
+pub fn version() -> Value {
+    query(
+        "version",
+        "The dictionary version the names must come from",
+        integer(),
+    )
+}
+
+/// The `output` parameter of routes that negotiate their output.
+pub fn output() -> Value {
+    query(
+        "output",
+        "How to render the response, overriding the Accept header",
+        Output::schema(),
+    )
+}
+
+fn path_parameter(name: &str) -> Value {
+    json!({ "name": name, "in": "path", "required": true, "schema": string() })
+}
+
+/// What a route answers with when it succeeds.
+pub enum Content {
+    /// JSON as described
+    Json(fn() -> Value),
+    /// The output the client negotiated, with JSON as described
+    Negotiated(fn() -> Value),
+    /// A body of the given type that isn't described further
+    Other(&'static str),
+}
+
+/// A request body.
+pub enum Body {
+    Json(fn() -> Value),
+    Form(fn() -> Value),
+    /// A body that is verified byte for byte, so it is only described
+    /// loosely
+    Raw(&'static str),
+}
+
+pub struct Operation {
+    pub method: Method,
+    summary: &'static str,
+    parameters: fn() -> Vec<Value>,
+    body: Option<Body>,
+    status: u16,
+    content: Content,
+    /// The response in `components` that describes failures
+    error: &'static str,
+    /// The fields of the query struct, so tests can hold the parameters to
+    /// them
+    #[cfg(test)]
+    fields: fn() -> Vec<String>,
+    /// Parses a query string into the query struct
+    #[cfg(test)]
+    parse: fn(&str) -> bool,
+}
+
+impl Operation {
+    fn new<P>(method: Method, summary: &'static str, content: Content) -> Self
+    where
+        P: Parameters + DeserializeOwned,
+    {
+        Operation {
+            method,
+            summary,
+            parameters: P::parameters,
+            body: None,
+            status: 200,
+            content,
+            error: "Error",
+            #[cfg(test)]
+            fields: test::fields::<P>,
+            #[cfg(test)]
+            parse: |query| ::serde_urlencoded::from_str::<P>(query).is_ok(),
+        }
+    }
+
+    fn body(mut self, body: Body) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Marks an operation as part of v2, which reports failures in the same
+    /// schema as its names.
+    fn v2(mut self) -> Self {
+        self.error = "V2Error";
+        self
+    }
+}
+
+/// A resource of the app and the operations it serves. The name is the
+/// resource name the app registers it under.
+pub struct Route {
+    pub name: &'static str,
+    pub path: &'static str,
+    pub operations: Vec<Operation>,
+}
+
+fn route(name: &'static str, path: &'static str, operations: Vec<Operation>) -> Route {
+    Route {
+        name,
+        path,
+        operations,
+    }
+}
+
+/// Every route the app can serve. The app looks the paths up here, so a
+/// route can't be added to the app without being described.
+pub fn routes() -> Vec<Route> {
+    use self::Content::*;
+    vec![
+        route(
+            "home",
+            "/",
+            vec![Operation::new::<home::Params>(
+                Method::GET,
+                "A page for naming shas and finding the shas of names",
+                Other("text/html"),
+            )],
+        ),
+        route(
+            "metrics",
+            "/metrics",
+            vec![Operation::new::<NoParams>(
+                Method::GET,
+                "Prometheus metrics",
+                Other("text/plain"),
+            )],
+        ),
+        route(
+            "healthz",
+            "/healthz",
+            vec![Operation::new::<NoParams>(
+                Method::GET,
+                "Whether the server is serving",
+                Other("text/plain"),
+            )],
+        ),
+        route(
+            "readyz",
+            "/readyz",
+            vec![Operation::new::<NoParams>(
+                Method::GET,
+                "Whether names can be looked up",
+                Other("text/plain"),
+            )],
+        ),
+        route(
+            "version",
+            "/version",
+            vec![Operation::new::<NoParams>(
+                Method::GET,
+                "What the server is serving",
+                Json(Response::<Version>::schema),
+            )],
+        ),
+        route(
+            "openapi",
+            "/api/openapi.json",
+            vec![Operation::new::<NoParams>(
+                Method::GET,
+                "This document",
+                Other("application/json"),
+            )],
+        ),
+        route(
+            "dictionary",
+            "/api/dictionary/{kind}",
+            vec![Operation::new::<dictionary::Params>(
+                Method::GET,
+                "List the words of a kind",
+                Json(Response::<Page>::schema),
+            )],
+        ),
+        route(
+            "word",
+            "/api/dictionary/{kind}/{word}",
+            vec![Operation::new::<NoParams>(
+                Method::GET,
+                "Look up a word",
+                Json(Response::<Word>::schema),
+            )],
+        ),
+        route(
+            "index",
+            "/api/release-name",
+            vec![
+                Operation::new::<index::Params>(
+                    Method::GET,
+                    "Name many shas",
+                    Negotiated(Response::<BulkNames>::schema),
+                ),
+                Operation::new::<bulk::Params>(
+                    Method::POST,
+                    "Name many shas in order",
+                    Negotiated(Response::<Vec<bulk::Record>>::schema),
+                )
+                .body(Body::Json(bulk::Body::schema)),
+            ],
+        ),
+        route(
+            "allocate",
+            "/api/release-name/allocate",
+            vec![Operation::new::<allocate::Params>(
+                Method::POST,
+                "Generate names that aren't taken",
+                Negotiated(Response::<Vec<Name>>::schema),
+            )
+            .body(Body::Json(allocate::Body::schema))],
+        ),
+        route(
+            "random",
+            "/api/release-name/random",
+            vec![Operation::new::<random::Params>(
+                Method::GET,
+                "Generate random names, a list when a count is given",
+                Negotiated(random_names),
+            )],
+        ),
+        route(
+            "badge",
+            "/api/release-name/{sha}/badge.svg",
+            vec![Operation::new::<badge::Params>(
+                Method::GET,
+                "A badge with the name of a sha",
+                Other("image/svg+xml"),
+            )],
+        ),
+        route(
+            "show",
+            "/api/release-name/{sha}",
+            vec![Operation::new::<show::Params>(
+                Method::GET,
+                "Name a sha",
+                Negotiated(Response::<Name>::schema),
+            )],
+        ),
+        route(
+            "v2_names",
+            "/api/v2/names",
+            vec![
+                Operation::new::<v2::ListParams>(
+                    Method::GET,
+                    "Name many shas in order",
+                    Json(Names::schema),
+                )
+                .v2(),
+                Operation::new::<NoParams>(
+                    Method::POST,
+                    "Name many shas in order",
+                    Json(Names::schema),
+                )
+                .body(Body::Json(v2::Body::schema))
+                .v2(),
+            ],
+        ),
+        route(
+            "v2_name",
+            "/api/v2/names/{sha}",
+            vec![Operation::new::<v2::Params>(Method::GET, "Name a sha", Json(Names::schema)).v2()],
+        ),
+        route(
+            "v2_random",
+            "/api/v2/random",
+            vec![Operation::new::<v2::RandomParams>(
+                Method::GET,
+                "Generate random names",
+                Json(Names::schema),
+            )
+            .v2()],
+        ),
+        route(
+            "reservations",
+            "/api/reservations",
+            vec![
+                Operation::new::<reservation::Params>(
+                    Method::GET,
+                    "List reservations",
+                    Json(Response::<Vec<Reservation>>::schema),
+                ),
+                Operation::new::<NoParams>(
+                    Method::POST,
+                    "Reserve a name, returning the held one with a 200 when there is one",
+                    Json(Response::<Reservation>::schema),
+                )
+                .body(Body::Json(reservation::Body::schema))
+                .status(201),
+            ],
+        ),
+        route(
+            "reservation",
+            "/api/reservations/{name}",
+            vec![
+                Operation::new::<NoParams>(
+                    Method::GET,
+                    "Look up a reservation",
+                    Json(Response::<Reservation>::schema),
+                ),
+                Operation::new::<reservation::ReleaseParams>(
+                    Method::DELETE,
+                    "Release a reservation held by the owner",
+                    Json(Response::<Reservation>::schema),
+                ),
+            ],
+        ),
+        route(
+            "webhook",
+            "/api/webhook",
+            vec![Operation::new::<webhook::Params>(
+                Method::POST,
+                "Name the commits of a GitHub or GitLab push",
+                Json(Response::<Summary>::schema),
+            )
+            .body(Body::Raw("application/json"))],
+        ),
+        route(
+            "slash",
+            "/api/slash",
+            vec![Operation::new::<NoParams>(
+                Method::POST,
+                "Answer a Slack or Mattermost slash command",
+                Other("application/json"),
+            )
+            .body(Body::Form(slash::Command::schema))],
+        ),
+    ]
+}
+
+/// The path of a route, panicking when it hasn't been described.
+pub fn path(name: &str) -> &'static str {
+    routes()
+        .into_iter()
+        .find(|route| route.name == name)
+        .map(|route| route.path)
+        .unwrap_or_else(|| panic!("the {} route is missing from the OpenAPI document", name))
+}
+
+fn negotiated(schema: Value) -> Value {
+    json!({
+        "application/json": { "schema": schema },
+        "text/plain": { "schema": string() },
+        "text/csv": { "schema": string() },
+        "text/html": { "schema": string() },
+    })
+}
+
+/// A single name, or a list of them when a count is given.
+fn random_names() -> Value {
+    json!({ "oneOf": [Response::<Name>::schema(), Response::<Vec<Name>>::schema()] })
+}
+
+fn operation(route: &Route, op: &Operation) -> Value {
+    let mut parameters: Vec<Value> = path_parameters(route.path)
+        .into_iter()
+        .map(path_parameter)
+        .collect();
+    parameters.extend((op.parameters)());
+
+    let success = match op.content {
+        Content::Json(schema) => json!({
+            "description": "Success",
+            "content": { "application/json": { "schema": schema() } },
+        }),
+        Content::Negotiated(schema) => json!({
+            "description": "Success, in the negotiated output",
+            "content": negotiated(schema()),
+        }),
+        Content::Other(content_type) => json!({
+            "description": "Success",
+            "content": { content_type: { "schema": string() } },
+        }),
+    };
+    let mut responses = Map::new();
+    responses.insert(op.status.to_string(), success);
+    responses.insert(
+        String::from("default"),
+        json!({ "$ref": format!("#/components/responses/{}", op.error) }),
+    );
+
+    let mut operation = json!({
+        "operationId": format!("{}_{}", route.name, op.method.as_str().to_lowercase()),
+        "summary": op.summary,
+        "parameters": parameters,
+        "responses": responses,
+    });
+    if let Some(ref body) = op.body {
+        let content = match *body {
+            Body::Json(schema) => json!({ "application/json": { "schema": schema() } }),
+            Body::Form(schema) => {
+                json!({ "application/x-www-form-urlencoded": { "schema": schema() } })
+            }
+            Body::Raw(content_type) => {
+                json!({ content_type: { "schema": { "type": "object" } } })
+            }
+        };
+        operation["requestBody"] = json!({ "required": true, "content": content });
+    }
+    operation
+}
+
+/// The names of the parameters in a path, in order.
+fn path_parameters(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{'))
+        .filter_map(|segment| segment.split('}').next())
+        .collect()
+}
+
+/// Builds the OpenAPI 3 document for every route.
+pub fn spec() -> Value {
+    let mut paths = Map::new();
+    for route in routes() {
+        let operations: Map<String, Value> = route
+            .operations
+            .iter()
+            .map(|op| (op.method.as_str().to_lowercase(), operation(&route, op)))
+            .collect();
+        paths.insert(route.path.to_string(), Value::Object(operations));
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "git-release-name",
+            "description": "Turns git shas into release names. Reservations, webhooks and \
+                            slash commands are only served when they are configured.",
+            "version": env!("CARGO_PKG_VERSION"),
+            "x-dictionary-version": DICTIONARY_VERSION,
+        },
+        "paths": paths,
+        // Keys are optional unless the server requires them.
+        "security": [{}, { "ApiKey": [] }, { "Bearer": [] }],
+        "components": {
+            "securitySchemes": {
+                "ApiKey": { "type": "apiKey", "in": "header", "name": "X-Api-Key" },
+                "Bearer": { "type": "http", "scheme": "bearer" },
+            },
+            "responses": {
+                "Error": {
+                    "description": "An error, with a machine readable code",
+                    "content": {
+                        "application/json": {
+                            "schema": object(&[("error", ApiError::schema())], &[]),
+                        },
+                    },
+                },
+                "V2Error": {
+                    "description": "An error, with the errors of any names",
+                    "content": {
+                        "application/json": { "schema": Names::schema() },
+                    },
+                },
+            },
+        },
+    })
+}
+
+pub fn handler(_: HttpRequest) -> Json<Value> {
+    Json(spec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::client::ClientRequest;
+    use actix_web::test::TestServer;
+    use serde::de::{self, Deserializer, Visitor};
+    use serde_json;
+    use std::collections::HashSet;
+    use testing::{fetch, send, server, SECRET};
+
+    /// A deserializer that fails with the names of the fields of the struct
+    /// it is asked for.
+    struct FieldNames;
+
+    impl<'de> Deserializer<'de> for FieldNames {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom(""))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom(fields.join(",")))
+        }
+
+        ::serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    /// The fields of a query struct, or none for structs without any.
+    pub fn fields<P: DeserializeOwned>() -> Vec<String> {
+        match P::deserialize(FieldNames) {
+            Ok(_) => panic!("field names can't be deserialized"),
+            Err(err) => err
+                .to_string()
+                .split(',')
+                .filter(|field| !field.is_empty())
+                .map(String::from)
+                .collect(),
+        }
+    }
+
+    fn example(name: &str) -> &'static str {
+        match name {
+            "sha" => "a9677113",
+            "kind" => "adjectives",
+            "word" => "timeless",
+            "name" => "forcibly endways holdback",
+            "shas" => "abc,123",
+            "owner" => "ci",
+            other => panic!("no example for the {} parameter", other),
+        }
+    }
+
+    /// A url for an operation, with examples for the path and required
+    /// query parameters.
+    fn url(route: &Route, op: &Operation) -> String {
+        let mut path = route.path.to_string();
+        for name in path_parameters(route.path) {
+            path = path.replace(&format!("{{{}}}", name), &example(name).replace(' ', "%20"));
+        }
+        let required: Vec<String> = (op.parameters)()
+            .into_iter()
+            .filter(|p| p["required"] == true)
+            .map(|p| {
+                let name = p["name"].as_str().unwrap().to_string();
+                format!("{}={}", name, example(&name))
+            })
+            .collect();
+        if required.is_empty() {
+            path
+        } else {
+            format!("{}?{}", path, required.join("&"))
+        }
+    }
+
+    /// The code of an error response, if it is one.
+    fn code(body: &str) -> Value {
+        // v2 routes list their errors.
+        serde_json::from_str::<Value>(body)
+            .map(|body| match body["errors"][0]["code"] {
+                Value::Null => body["error"]["code"].clone(),
+                ref code => code.clone(),
+            })
+            .unwrap_or(Value::Null)
+    }
+
+    /// A request for an operation that succeeds, with an example body.
+    fn request(srv: &mut TestServer, url: &str, route: &Route, op: &Operation) -> ClientRequest {
+        let mut req = srv.client(op.method.clone(), url);
+        req.header("Accept", "application/json");
+        let body = match (route.name, op.method.as_str()) {
+            ("index", "POST") => r#"{"shas": ["abc", "123"]}"#,
+            ("allocate", "POST") => r#"{"count": 2, "taken": ["forcibly endways holdback"]}"#,
+            ("v2_names", "POST") => r#"{"shas": ["abc", "123"]}"#,
+            ("reservations", "POST") => r#"{"owner": "ci", "purpose": "docs", "seed": 0}"#,
+            ("webhook", "POST") => {
+                req.header("X-Gitlab-Event", "Push Hook")
+                    .header("X-Gitlab-Token", SECRET);
+                include_str!("../tests/fixtures/gitlab_push.json")
+            }
+            _ => "",
+        };
+        req.content_type("application/json").body(body).unwrap()
+    }
+
+    /// Example bodies for an operation that takes JSON, which between them
+    /// set every property it describes.
+    fn bodies(route: &Route, op: &Operation) -> Vec<String> {
+        let options = format!(
+            r#""seed": 7, "format": "snake", "version": {}, "max_length": 40, "initial": "p""#,
+            DICTIONARY_VERSION
+        );
+        match (route.name, op.method.as_str()) {
+            ("index", "POST") => vec![
+                String::from(r#"["abc", "123"]"#),
+                format!(
+                    r#"{{"shas": ["abc"], "format": "snake", "version": {}}}"#,
+                    DICTIONARY_VERSION
+                ),
+            ],
+            ("allocate", "POST") => vec![format!(
+                r#"{{"count": 2, "taken": ["forcibly endways holdback"], {}}}"#,
+                options
+            )],
+            ("v2_names", "POST") => vec![format!(
+                r#"{{"shas": ["abc"], "case": "snake", "version": {}}}"#,
+                DICTIONARY_VERSION
+            )],
+            ("reservations", "POST") => vec![format!(
+                r#"{{"owner": "ci", "purpose": "docs", {}}}"#,
+                options
+            )],
+            (name, method) => panic!("no example body for {} {}", method, name),
+        }
+    }
+
+    /// The properties a schema describes, in any of its alternatives.
+    fn properties(schema: &Value) -> HashSet<String> {
+        let mut properties: HashSet<String> = schema["properties"]
+            .as_object()
+            .into_iter()
+            .flat_map(|properties| properties.keys().cloned())
+            .collect();
+        for schema in schema["oneOf"].as_array().into_iter().flatten() {
+            properties.extend(self::properties(schema));
+        }
+        properties
+    }
+
+    /// Checks a value against a schema, allowing only the properties it
+    /// describes and requiring the ones it requires.
+    fn conforms(value: &Value, schema: &Value, at: &str) -> Result<(), String> {
+        if let Some(schemas) = schema["oneOf"].as_array() {
+            return if schemas.iter().any(|s| conforms(value, s, at).is_ok()) {
+                Ok(())
+            } else {
+                Err(format!("{} matches none of the schemas: {}", at, value))
+            };
+        }
+        if value.is_null() && schema["nullable"] == true {
+            return Ok(());
+        }
+        let matches = match schema["type"].as_str() {
+            Some("object") => {
+                let object = value
+                    .as_object()
+                    .ok_or_else(|| format!("{} is not an object: {}", at, value))?;
+                for name in schema["required"].as_array().into_iter().flatten() {
+                    let name = name.as_str().unwrap();
+                    if !object.contains_key(name) {
+                        return Err(format!("{} is missing the required {}", at, name));
+                    }
+                }
+                for (name, value) in object {
+                    let at = format!("{}.{}", at, name);
+                    match schema["properties"].get(name) {
+                        Some(property) => conforms(value, property, &at)?,
+                        None if schema["additionalProperties"].is_object() => {
+                            conforms(value, &schema["additionalProperties"], &at)?
+                        }
+                        None => return Err(format!("{} is not described", at)),
+                    }
+                }
+                true
+            }
+            Some("array") => {
+                let items = value
+                    .as_array()
+                    .ok_or_else(|| format!("{} is not an array: {}", at, value))?;
+                for (i, item) in items.iter().enumerate() {
+                    conforms(item, &schema["items"], &format!("{}[{}]", at, i))?;
+                }
+                true
+            }
+            Some("string") => value.as_str().is_some_and(|value| {
+                schema["enum"]
+                    .as_array()
+                    .is_none_or(|values| values.iter().any(|v| v == value))
+            }),
+            Some("integer") => value.is_u64() || value.is_i64(),
+            Some("number") => value.is_number(),
+            Some("boolean") => value.is_boolean(),
+            _ => true,
+        };
+        if matches {
+            Ok(())
+        } else {
+            Err(format!("{} doesn't match {}: {}", at, schema, value))
+        }
+    }
+
+    #[test]
+    fn every_route_is_served() {
+        let mut srv = server();
+        for route in routes() {
+            for op in &route.operations {
+                let url = url(&route, op);
+                let (status, body) = fetch(&mut srv, op.method.clone(), &url, "{}");
+                let code = code(&body);
+                assert!(
+                    status != 405 && code != "not_found",
+                    "{} {} is described but not served: {} {}",
+                    op.method,
+                    url,
+                    status,
+                    body
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_described_methods_are_served() {
+        let mut srv = server();
+        let methods = [
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ];
+        for route in routes() {
+            for method in &methods {
+                let op = route.operations.iter().find(|op| op.method == *method);
+                let url = url(&route, op.unwrap_or(&route.operations[0]));
+                let (status, body) = fetch(&mut srv, method.clone(), &url, "{}");
+                let served = status != 405 && code(&body) != "not_found";
+                assert_eq!(
+                    served,
+                    op.is_some(),
+                    "{} {} is {} but answered {} {}",
+                    method,
+                    url,
+                    if op.is_some() {
+                        "described"
+                    } else {
+                        "not described"
+                    },
+                    status,
+                    body
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parameters_match_the_query_structs() {
+        for route in routes() {
+            for op in &route.operations {
+                let params = (op.parameters)();
+                let mut described: Vec<String> = params
+                    .iter()
+                    .map(|p| p["name"].as_str().unwrap().to_string())
+                    .collect();
+                let mut fields = (op.fields)();
+                described.sort_unstable();
+                fields.sort_unstable();
+                assert_eq!(described, fields, "{} {}", op.method, route.path);
+
+                let required: Vec<String> = params
+                    .iter()
+                    .filter(|p| p["required"] == true)
+                    .map(|p| p["name"].as_str().unwrap())
+                    .map(|name| format!("{}={}", name, example(name)))
+                    .collect();
+                assert!((op.parse)(&required.join("&")), "{}", route.path);
+                for missing in &required {
+                    let query: Vec<&str> = required
+                        .iter()
+                        .filter(|param| param != &missing)
+                        .map(String::as_str)
+                        .collect();
+                    assert!(
+                        !(op.parse)(&query.join("&")),
+                        "{} {} is described as required but parses without it",
+                        route.path,
+                        missing
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn responses_follow_their_schemas() {
+        let spec = spec();
+        let mut srv = server();
+        for route in routes() {
+            for op in &route.operations {
+                let schema = match op.content {
+                    Content::Json(schema) | Content::Negotiated(schema) => schema(),
+                    Content::Other(_) => continue,
+                };
+                let url = url(&route, op);
+                let req = request(&mut srv, &url, &route, op);
+                let (status, _, body) = send(&mut srv, req);
+                assert_eq!(status, op.status, "{} {}: {}", op.method, url, body);
+                let body: Value = serde_json::from_str(&body).unwrap();
+                let at = format!("{} {}", op.method, url);
+                conforms(&body, &schema, &at).unwrap_or_else(|err| panic!("{}", err));
+
+                // Every naming route rejects other dictionary versions.
+                let params = (op.parameters)();
+                if !params.iter().any(|p| p["name"] == "version") {
+                    continue;
+                }
+                let separator = if url.contains('?') { '&' } else { '?' };
+                let url = format!("{}{}version=0", url, separator);
+                let req = request(&mut srv, &url, &route, op);
+                let (status, _, body) = send(&mut srv, req);
+                assert_eq!(status, 400, "{} {}: {}", op.method, url, body);
+                let body: Value = serde_json::from_str(&body).unwrap();
+                let error = &spec["components"]["responses"][op.error]["content"]
+                    ["application/json"]["schema"];
+                let at = format!("{} {}", op.method, url);
+                conforms(&body, error, &at).unwrap_or_else(|err| panic!("{}", err));
+            }
+        }
+    }
+
+    #[test]
+    fn request_bodies_are_read_as_described() {
+        let mut srv = server();
+        for route in routes() {
+            for op in &route.operations {
+                let schema = match op.body {
+                    Some(Body::Json(schema)) => schema(),
+                    _ => continue,
+                };
+                let mut unused = properties(&schema);
+                for body in bodies(&route, op) {
+                    let at = format!("{} {}", op.method, route.path);
+                    let value: Value = serde_json::from_str(&body).unwrap();
+                    conforms(&value, &schema, &at).unwrap_or_else(|err| panic!("{}", err));
+                    unused.retain(|name| value.get(name).is_none());
+
+                    let url = url(&route, op);
+                    let (status, resp) = fetch(&mut srv, op.method.clone(), &url, &body);
+                    assert_eq!(status, op.status, "{} {}: {}", at, body, resp);
+                }
+                assert!(
+                    unused.is_empty(),
+                    "{} {} has no example body with {:?}",
+                    op.method,
+                    route.path,
+                    unused
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn schemas_are_checked_strictly() {
+        let schema = object(
+            &[("name", string()), ("count", nullable(integer()))],
+            &["count"],
+        );
+        assert!(conforms(&json!({ "name": "a", "count": null }), &schema, "").is_ok());
+        assert!(conforms(&json!({ "count": 1 }), &schema, "").is_err());
+        assert!(conforms(&json!({ "name": "a", "extra": 1 }), &schema, "").is_err());
+        assert!(conforms(&json!({ "name": 1 }), &schema, "").is_err());
+    }
+
+    #[test]
+    fn operations_are_unique_and_complete() {
+        let spec = spec();
+        let mut ids = HashSet::new();
+        for route in routes() {
+            for op in &route.operations {
+                let op = &spec["paths"][route.path][op.method.as_str().to_lowercase()];
+                assert!(ids.insert(op["operationId"].to_string()));
+                let params: Vec<&str> = op["parameters"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter(|p| p["in"] == "path")
+                    .map(|p| p["name"].as_str().unwrap())
+                    .collect();
+                assert_eq!(params, path_parameters(route.path));
+            }
+        }
+    }
+
+    #[test]
+    fn paths_are_found_by_route_name() {
+        assert_eq!(path("show"), "/api/release-name/{sha}");
+        assert_eq!(
+            path_parameters("/api/dictionary/{kind}/{word}"),
+            vec!["kind", "word"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "missing from the OpenAPI document")]
+    fn undescribed_routes_panic() {
+        path("undescribed");
+    }
+}
@@ -0,0 +1,234 @@
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{http::StatusCode, HttpMessage, HttpRequest, HttpResponse};
+use error::ApiError;
+use openapi::{self, Schema};
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::str::FromStr;
+
+/// The representations a response can be rendered as.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Output {
+    Text,
+    Json,
+    Csv,
+    Html,
+}
+
+impl FromStr for Output {
+    type Err = ApiError;
+
+    fn from_str(output: &str) -> Result<Output, ApiError> {
+        match output {
+            "text" => Ok(Output::Text),
+            "json" => Ok(Output::Json),
+            "csv" => Ok(Output::Csv),
+            "html" => Ok(Output::Html),
+            _ => Err(ApiError::unknown_output(output)),
+        }
+    }
+}
+
+impl Schema for Output {
+    fn schema() -> Value {
+        openapi::string_enum(&["text", "json", "csv", "html"])
+    }
+}
+
+impl Output {
+    /// Picks the output for a request. The `output` query parameter wins,
+    /// then the most preferred type in the `Accept` header. When neither
+    /// names a supported type the route's default is used.
+    pub fn negotiate<S>(
+        req: &HttpRequest<S>,
+        output: Option<&str>,
+        default: Output,
+    ) -> Result<Output, ApiError> {
+        if let Some(output) = output {
+            return output.parse();
+        }
+        Ok(req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .and_then(|accept| Output::from_accept(accept, default))
+            .unwrap_or(default))
+    }
+
+    fn from_accept(accept: &str, default: Output) -> Option<Output> {
+        let mut best: Option<(Output, f32)> = None;
+        for range in accept.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let output = match parts.next().unwrap_or("") {
+                "text/plain" => Output::Text,
+                "application/json" => Output::Json,
+                "text/csv" => Output::Csv,
+                "text/html" => Output::Html,
+                "*/*" => default,
+                _ => continue,
+            };
+            let quality = parts
+                .filter_map(|param| param.split_once('='))
+                .find(|&(key, _)| key.trim() == "q")
+                .and_then(|(_, q)| q.trim().parse().ok())
+                .unwrap_or(1.0);
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((output, quality));
+            }
+        }
+        best.map(|(output, _)| output)
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Output::Text => "text/plain; charset=utf-8",
+            Output::Json => "application/json",
+            Output::Csv => "text/csv; charset=utf-8",
+            Output::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    /// Renders the data as a response. JSON is serialized from `data` while
+    /// the other representations are built from the rows.
+    pub fn respond<T: Serialize>(self, data: &T, rows: &[Row]) -> HttpResponse {
+        let body = match self {
+            Output::Json => serde_json::to_string(data).expect("responses serialize"),
+            Output::Text => text(rows),
+            Output::Csv => csv(rows),
+            Output::Html => html(rows),
+        };
+        HttpResponse::build(StatusCode::OK)
+            .header(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(self.content_type()),
+            )
+            .body(body)
+    }
+}
+
+/// A sha and the name it was given, if it could be named.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Row<'a> {
+    pub sha: &'a str,
+    pub name: Option<&'a str>,
+}
+
+impl<'a> Row<'a> {
+    pub fn new(sha: &'a str, name: Option<&'a str>) -> Self {
+        Row { sha, name }
+    }
+}
+
+/// One name per line, in order. Shas that could not be named are left as
+/// blank lines so the lines still match up with the input.
+fn text(rows: &[Row]) -> String {
+    rows.iter()
+        .map(|row| row.name.unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn csv(rows: &[Row]) -> String {
+    let mut out = String::from("sha,name\r\n");
+    for row in rows {
+        out.push_str(&csv_field(row.sha));
+        out.push(',');
+        out.push_str(&csv_field(row.name.unwrap_or("")));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn html(rows: &[Row]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Release names</title></head>\n\
+         <body>\n<table>\n<thead><tr><th>sha</th><th>name</th></tr></thead>\n<tbody>\n",
+    );
+    for row in rows {
+        out.push_str(&format!(
+            "<tr><td><code>{}</code></td><td>{}</td></tr>\n",
+            escape(row.sha),
+            escape(row.name.unwrap_or(""))
+        ));
+    }
+    out.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    out
+}
+
+/// Escapes text for use in html content or attributes.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn negotiate(accept: &str, output: Option<&str>) -> Result<Output, ApiError> {
+        let req = TestRequest::with_header("Accept", accept).finish();
+        Output::negotiate(&req, output, Output::Json)
+    }
+
+    #[test]
+    fn the_query_parameter_wins() {
+        assert_eq!(negotiate("text/plain", Some("csv")), Ok(Output::Csv));
+        assert_eq!(
+            negotiate("text/plain", Some("yaml")),
+            Err(ApiError::unknown_output("yaml"))
+        );
+    }
+
+    #[test]
+    fn the_most_preferred_type_is_picked() {
+        assert_eq!(negotiate("text/plain", None), Ok(Output::Text));
+        assert_eq!(
+            negotiate("text/html;q=0.5, text/csv;q=0.9", None),
+            Ok(Output::Csv)
+        );
+        assert_eq!(
+            negotiate("text/html,application/xhtml+xml,*/*;q=0.8", None),
+            Ok(Output::Html)
+        );
+    }
+
+    #[test]
+    fn unsupported_types_use_the_default() {
+        assert_eq!(negotiate("*/*", None), Ok(Output::Json));
+        assert_eq!(negotiate("image/png", None), Ok(Output::Json));
+        assert_eq!(negotiate("text/plain;q=0", None), Ok(Output::Json));
+    }
+
+    #[test]
+    fn rows_render_in_order() {
+        let rows = [
+            Row::new("abc", Some("ambitiously timeless gemot")),
+            Row::new("x,y", None),
+        ];
+        assert_eq!(text(&rows), "ambitiously timeless gemot\n");
+        assert_eq!(
+            csv(&rows),
+            "sha,name\r\nabc,ambitiously timeless gemot\r\n\"x,y\",\r\n"
+        );
+        assert!(html(&[Row::new("<b>", None)]).contains("<td><code>&lt;b&gt;</code></td>"));
+    }
+}
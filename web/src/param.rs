@@ -1,7 +1,110 @@
-use git_release_name::Case;
+use actix_web::{Error, FromRequest, HttpRequest};
+use error::ApiError;
+use git_release_name::{Case, DICTIONARY_VERSION};
+use openapi::{self, Schema};
+use serde::de::DeserializeOwned;
 use serde::{de, Deserialize, Deserializer};
+use serde_json::Value;
+use serde_urlencoded;
 use std::ops::Deref;
+use std::str::FromStr;
 
+/// Extracts the query string like `actix_web::Query` but reports failures
+/// with an `ApiError`.
+pub struct Query<T>(T);
+
+impl<T> Deref for Query<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T, S> FromRequest<S> for Query<T>
+where
+    T: DeserializeOwned,
+{
+    type Config = ();
+    type Result = Result<Self, Error>;
+
+    fn from_request(req: &HttpRequest<S>, _: &Self::Config) -> Self::Result {
+        query(req).map(Query).map_err(Error::from)
+    }
+}
+
+/// Parses the query string of a request, for handlers that report failures
+/// themselves.
+pub fn query<T, S>(req: &HttpRequest<S>) -> Result<T, ApiError>
+where
+    T: DeserializeOwned,
+{
+    serde_urlencoded::from_str::<T>(req.query_string())
+        .map_err(|e| ApiError::invalid_query(&e.to_string()))
+}
+
+/// Checks that the requested dictionary version, if any, is the one being
+/// served.
+pub fn version(version: Option<&str>) -> Result<u32, ApiError> {
+    match version {
+        None => Ok(DICTIONARY_VERSION),
+        Some(v) => match v.parse() {
+            Ok(DICTIONARY_VERSION) => Ok(DICTIONARY_VERSION),
+            _ => Err(ApiError::unknown_dictionary_version(v)),
+        },
+    }
+}
+
+/// Checks a sha taken from a path before it is looked up. Only ascii
+/// hexadecimal characters can name anything.
+pub fn sha(sha: &str) -> Result<&str, ApiError> {
+    if !sha.is_empty() && sha.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(sha)
+    } else {
+        Err(ApiError::non_hexadecimal(sha))
+    }
+}
+
+#[cfg(test)]
+mod sha_test {
+    use super::*;
+
+    #[test]
+    fn only_hexadecimal_shas_are_accepted() {
+        assert_eq!(sha("abc123DEF"), Ok("abc123DEF"));
+        for bad in &["", "xyz", "abc 123", "abcdefg\u{e9}", "\u{e9}\u{e9}"] {
+            assert_eq!(sha(bad), Err(ApiError::non_hexadecimal(bad)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod version_test {
+    use super::*;
+
+    #[test]
+    fn it_accepts_the_current_version() {
+        assert_eq!(version(None), Ok(DICTIONARY_VERSION));
+        assert_eq!(
+            version(Some(&DICTIONARY_VERSION.to_string())),
+            Ok(DICTIONARY_VERSION)
+        );
+    }
+
+    #[test]
+    fn it_rejects_other_versions() {
+        assert_eq!(
+            version(Some("0")),
+            Err(ApiError::unknown_dictionary_version("0"))
+        );
+        assert_eq!(
+            version(Some("latest")),
+            Err(ApiError::unknown_dictionary_version("latest"))
+        );
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct CSV(Vec<String>);
 
 impl Deref for CSV {
@@ -22,6 +125,12 @@ impl<'de> Deserialize<'de> for CSV {
     }
 }
 
+impl Schema for CSV {
+    fn schema() -> Value {
+        json!({ "type": "string", "pattern": "^[^,]*(,[^,]*)*$" })
+    }
+}
+
 #[cfg(test)]
 mod csv_test {
     use super::*;
@@ -30,11 +139,16 @@ mod csv_test {
     #[test]
     fn it_can_parse_to_a_vec() {
         let data = "\"abc,123\"";
-        let csv: CSV = serde_json::from_str(&data).unwrap();
+        let csv: CSV = serde_json::from_str(data).unwrap();
         assert_eq!(*csv, vec!["abc", "123"]);
     }
 }
 
+/// The names of every case, in the order they are offered.
+pub const CASES: &[&str] = &[
+    "lower", "upper", "title", "sentence", "snake", "kebab", "pascal", "camel",
+];
+
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub struct Format(Case);
 
@@ -58,12 +172,36 @@ impl<'de> Deserialize<'de> for Format {
     }
 }
 
+impl Format {
+    /// Parses an optional query parameter, defaulting to lower case.
+    pub fn from_param(format: Option<&str>) -> Result<Format, ApiError> {
+        format.map_or(Ok(Case::Lower.into()), str::parse)
+    }
+}
+
+impl FromStr for Format {
+    type Err = ApiError;
+
+    fn from_str(format: &str) -> Result<Format, ApiError> {
+        format
+            .parse::<Case>()
+            .map(Format)
+            .map_err(|_| ApiError::unknown_format(format))
+    }
+}
+
 impl From<Case> for Format {
     fn from(case: Case) -> Format {
         Format(case)
     }
 }
 
+impl Schema for Format {
+    fn schema() -> Value {
+        openapi::string_enum(CASES)
+    }
+}
+
 #[cfg(test)]
 mod format_test {
     use super::*;
@@ -72,7 +210,21 @@ mod format_test {
     #[test]
     fn it_can_parse_to_a_vec() {
         let data = "\"snake\"";
-        let fmt: Format = serde_json::from_str(&data).unwrap();
+        let fmt: Format = serde_json::from_str(data).unwrap();
         assert_eq!(*fmt, Case::Snake);
     }
+
+    #[test]
+    fn it_defaults_to_lower_case() {
+        assert_eq!(Format::from_param(None), Ok(Format(Case::Lower)));
+        assert_eq!(Format::from_param(Some("kebab")), Ok(Format(Case::Kebab)));
+    }
+
+    #[test]
+    fn it_reports_unknown_formats() {
+        assert_eq!(
+            Format::from_param(Some("shouty")),
+            Err(ApiError::unknown_format("shouty"))
+        );
+    }
 }
@@ -1,26 +1,135 @@
-use super::Response;
-use actix_web::{Json, Query};
-use git_release_name::{self, Case};
-use param::Format;
+use super::{Name, Response};
+use access;
+use actix_web::http::header::HeaderValue;
+use actix_web::{HttpRequest, HttpResponse};
+use cache;
+use error::ApiError;
+use git_release_name::{Case, RandomShas};
+use metrics;
+use openapi::{self, Parameters};
+use output::{Output, Row};
+use param::{self, Format, Query};
 use rand;
+use serde_json::Value;
+
+/// The most names that can be generated in a single request.
+pub const MAX_COUNT: usize = 1000;
+
+/// The header the seed of the generated names is returned in.
+pub const SEED: &str = "x-random-seed";
 
 #[derive(Deserialize)]
 pub struct Params {
-    format: Option<Format>,
+    format: Option<String>,
+    version: Option<String>,
+    output: Option<String>,
+    count: Option<usize>,
+    seed: Option<u64>,
 }
 
-#[derive(Serialize)]
-pub struct Name {
-    name: String,
-    sha: String,
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![
+            openapi::format(),
+            openapi::version(),
+            openapi::output(),
+            openapi::query(
+                "count",
+                "How many names to generate, returning a list",
+                json!({ "type": "integer", "minimum": 0, "maximum": MAX_COUNT }),
+            ),
+            openapi::query(
+                "seed",
+                "Generates the same names every time",
+                openapi::integer(),
+            ),
+        ]
+    }
 }
 
-pub fn handler(q: Query<Params>) -> Json<Response<Name>> {
-    let format = q.format.unwrap_or(Case::Lower.into());
-    let sha = format!("{:08x}", rand::random::<u32>());
-    let name = git_release_name::lookup(&sha)
-        .map(|p| p.with_case(*format).to_string())
-        .unwrap_or(String::new());
+/// Generates `count` names from the seed, in order.
+fn names(case: Case, seed: u64, count: usize) -> Result<Vec<Name>, ApiError> {
+    RandomShas::new(seed)
+        .take(count)
+        .map(|sha| {
+            metrics::lookup(&sha)
+                .map(|p| Name::new(p.with_case(case).to_string(), sha.clone()))
+                .map_err(|e| ApiError::lookup(&sha, e))
+        })
+        .collect()
+}
+
+pub fn handler(mut req: HttpRequest, q: Query<Params>) -> Result<HttpResponse, ApiError> {
+    let format = Format::from_param(q.format.as_deref())?;
+    param::version(q.version.as_deref())?;
+    let output = Output::negotiate(&req, q.output.as_deref(), Output::Json)?;
+    let count = q.count.unwrap_or(1);
+    if count > MAX_COUNT {
+        return Err(ApiError::too_many_shas(count, MAX_COUNT));
+    }
+    access::shas(&mut req, count);
+
+    // Random names are never cached, seeded or not.
+    let seed = q.seed.unwrap_or_else(rand::random);
+    let names = names(*format, seed, count)?;
+    let rows: Vec<Row> = names
+        .iter()
+        .map(|name| Row::new(name.sha(), Some(name.name())))
+        .collect();
+    // Without a count the single name is returned on its own, as it always
+    // has been.
+    let mut resp = match q.count {
+        Some(_) => output.respond(&Response::new(&names), &rows),
+        None => output.respond(&Response::new(&names[0]), &rows),
+    };
+    resp.headers_mut().insert(
+        SEED,
+        HeaderValue::from_str(&seed.to_string()).expect("seeds are numeric"),
+    );
+    Ok(cache::no_store(resp))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::http::{header, Method};
+    use serde_json;
+    use testing::{send, server};
+
+    #[test]
+    fn seeds_give_the_same_names() {
+        let names = serde_json::to_value(names(Case::Kebab, 0, 2).unwrap()).unwrap();
+        assert_eq!(
+            names,
+            json!([
+                { "name": "forcibly-endways-holdback", "sha": "e220a839" },
+                { "name": "unpeacefully-costly-protest", "sha": "6e789e6a" },
+            ])
+        );
+    }
+
+    #[test]
+    fn the_most_names_can_be_generated() {
+        assert!(names(Case::Lower, 0, MAX_COUNT).is_ok());
+    }
 
-    Json(Response::new(Name { name, sha }))
+    #[test]
+    fn random_names_are_never_cached() {
+        let mut srv = server();
+        for url in &[
+            "/api/release-name/random",
+            "/api/release-name/random?seed=0",
+            "/api/v2/random?seed=0&count=2",
+        ] {
+            let req = srv
+                .client(Method::GET, url)
+                .header(header::IF_NONE_MATCH, "*")
+                .finish()
+                .unwrap();
+            let (status, headers, _) = send(&mut srv, req);
+            assert_eq!(status, 200, "{}", url);
+            assert_eq!(headers[header::CACHE_CONTROL], "no-store", "{}", url);
+            assert!(headers.get(header::ETAG).is_none(), "{}", url);
+        }
+    }
 }
@@ -0,0 +1,300 @@
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::{Middleware, Started};
+use actix_web::{HttpRequest, Result};
+use auth::Client;
+use error::ApiError;
+use serde::de::{self, Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use v2;
+
+/// The route whose rate applies to routes without one of their own.
+pub const ANY_ROUTE: &str = "*";
+
+/// How many buckets are kept before the least recently used are forgotten.
+const MAX_BUCKETS: usize = 100_000;
+
+/// A number of requests per period, such as `60/minute`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Rate {
+    pub count: u32,
+    pub per: Duration,
+}
+
+impl FromStr for Rate {
+    type Err = ();
+
+    fn from_str(rate: &str) -> Result<Rate, ()> {
+        let (count, per) = rate.split_once('/').ok_or(())?;
+        let count = count.trim().parse().map_err(|_| ())?;
+        let per = match per.trim() {
+            "s" | "second" => Duration::from_secs(1),
+            "m" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(3600),
+            _ => return Err(()),
+        };
+        if count == 0 {
+            return Err(());
+        }
+        Ok(Rate { count, per })
+    }
+}
+
+impl Display for Rate {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let per = match self.per.as_secs() {
+            1 => "second",
+            60 => "minute",
+            _ => "hour",
+        };
+        write!(f, "{}/{}", self.count, per)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rate {
+    fn deserialize<D>(d: D) -> Result<Rate, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(d)?;
+        s.parse()
+            .map_err(|_| de::Error::custom("expected a rate such as 60/minute"))
+    }
+}
+
+/// A token bucket that holds up to `count` requests and refills at the
+/// rate.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, rate: Rate, now: Instant) {
+        let per_second = f64::from(rate.count) / rate.per.as_secs_f64();
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second).min(f64::from(rate.count));
+        self.updated = now;
+    }
+
+    /// Takes a token, or says how long until there will be one.
+    fn take(&mut self, rate: Rate, now: Instant) -> Result<(), Duration> {
+        self.refill(rate, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let per_second = f64::from(rate.count) / rate.per.as_secs_f64();
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / per_second))
+        }
+    }
+}
+
+type Key = (String, Client);
+
+/// Buckets by route and client, holding at most `max` of them. Each bucket
+/// is stamped with when it was last used so the least recently used one can
+/// be found without a scan, and forgetting it only lets that client start
+/// again with a full bucket.
+#[derive(Debug)]
+struct Buckets {
+    buckets: HashMap<Key, (Bucket, u64)>,
+    by_use: BTreeMap<u64, Key>,
+    uses: u64,
+    max: usize,
+}
+
+impl Buckets {
+    fn new(max: usize) -> Buckets {
+        Buckets {
+            buckets: HashMap::new(),
+            by_use: BTreeMap::new(),
+            uses: 0,
+            max,
+        }
+    }
+
+    /// The bucket for a key, made full if it is new or was forgotten.
+    fn get(&mut self, key: Key, full: Bucket) -> &mut Bucket {
+        self.uses += 1;
+        let stamp = self.uses;
+        match self.buckets.get_mut(&key) {
+            Some(&mut (_, ref mut used)) => {
+                self.by_use.remove(used);
+                *used = stamp;
+            }
+            None => {
+                while self.buckets.len() >= self.max {
+                    match self.by_use.pop_first() {
+                        Some((_, oldest)) => self.buckets.remove(&oldest),
+                        None => break,
+                    };
+                }
+                self.buckets.insert(key.clone(), (full, stamp));
+            }
+        }
+        self.by_use.insert(stamp, key.clone());
+        &mut self
+            .buckets
+            .get_mut(&key)
+            .expect("the bucket was just used")
+            .0
+    }
+}
+
+impl Default for Buckets {
+    fn default() -> Buckets {
+        Buckets::new(MAX_BUCKETS)
+    }
+}
+
+/// Limits how often each client may call each route. Clients are told how
+/// long to wait with `Retry-After` when they run out.
+#[derive(Debug, Default, Clone)]
+pub struct RateLimiter {
+    rates: Arc<BTreeMap<String, Rate>>,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(rates: BTreeMap<String, Rate>) -> RateLimiter {
+        RateLimiter {
+            rates: Arc::new(rates),
+            buckets: Arc::default(),
+        }
+    }
+
+    fn rate(&self, route: &str) -> Option<Rate> {
+        self.rates
+            .get(route)
+            .or_else(|| self.rates.get(ANY_ROUTE))
+            .cloned()
+    }
+
+    fn take(&self, route: &str, client: Client, now: Instant) -> Result<(), Duration> {
+        let rate = match self.rate(route) {
+            Some(rate) => rate,
+            None => return Ok(()),
+        };
+        let full = Bucket {
+            tokens: f64::from(rate.count),
+            updated: now,
+        };
+        self.buckets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get((route.to_string(), client), full)
+            .take(rate, now)
+    }
+}
+
+impl<S> Middleware<S> for RateLimiter {
+    fn start(&self, req: &mut HttpRequest<S>) -> Result<Started> {
+        let route = match req.resource().name() {
+            "" => "unmatched",
+            name => name,
+        };
+        match self.take(route, Client::of(req), Instant::now()) {
+            Ok(()) => Ok(Started::Done),
+            Err(wait) => {
+                let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                let mut resp = v2::error_response(req, &ApiError::rate_limited(seconds));
+                resp.headers_mut().insert(
+                    header::RETRY_AFTER,
+                    HeaderValue::from_str(&seconds.to_string()).expect("numbers are valid headers"),
+                );
+                Ok(Started::Response(resp))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn limiter(rates: &[(&str, &str)]) -> RateLimiter {
+        RateLimiter::new(
+            rates
+                .iter()
+                .map(|&(route, rate)| (route.to_string(), rate.parse().unwrap()))
+                .collect(),
+        )
+    }
+
+    fn client(name: &str) -> Client {
+        Client::Key(name.to_string())
+    }
+
+    #[test]
+    fn rates_are_parsed() {
+        assert_eq!(
+            "60/minute".parse(),
+            Ok(Rate {
+                count: 60,
+                per: Duration::from_secs(60)
+            })
+        );
+        assert_eq!("5/s".parse::<Rate>().unwrap().to_string(), "5/second");
+        assert!("0/minute".parse::<Rate>().is_err());
+        assert!("60/fortnight".parse::<Rate>().is_err());
+        assert!("sixty".parse::<Rate>().is_err());
+    }
+
+    #[test]
+    fn buckets_run_out_and_refill() {
+        let limiter = limiter(&[("random", "2/minute")]);
+        let now = Instant::now();
+        assert_eq!(limiter.take("random", client("ci"), now), Ok(()));
+        assert_eq!(limiter.take("random", client("ci"), now), Ok(()));
+        assert_eq!(
+            limiter.take("random", client("ci"), now),
+            Err(Duration::from_secs(30))
+        );
+        let later = now + Duration::from_secs(30);
+        assert_eq!(limiter.take("random", client("ci"), later), Ok(()));
+    }
+
+    #[test]
+    fn clients_and_routes_have_their_own_buckets() {
+        let limiter = limiter(&[("random", "1/hour"), (ANY_ROUTE, "1/second")]);
+        let now = Instant::now();
+        assert!(limiter.take("random", client("ci"), now).is_ok());
+        assert!(limiter.take("random", client("qa"), now).is_ok());
+        assert!(limiter.take("index", client("ci"), now).is_ok());
+        assert!(limiter.take("random", client("ci"), now).is_err());
+        assert!(limiter.take("index", client("ci"), now).is_err());
+    }
+
+    #[test]
+    fn the_least_recently_used_bucket_is_forgotten() {
+        let limiter = limiter(&[("random", "1/hour")]);
+        *limiter.buckets.lock().unwrap() = Buckets::new(2);
+        let now = Instant::now();
+        assert!(limiter.take("random", client("ci"), now).is_ok());
+        assert!(limiter.take("random", client("qa"), now).is_ok());
+        assert!(limiter.take("random", client("ci"), now).is_err());
+        // qa's bucket is now the least recently used and makes way.
+        assert!(limiter.take("random", client("dev"), now).is_ok());
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.buckets.len(), 2);
+        assert_eq!(buckets.by_use.len(), 2);
+        drop(buckets);
+        assert!(limiter.take("random", client("ci"), now).is_err());
+        assert!(limiter.take("random", client("qa"), now).is_ok());
+        assert!(limiter.take("random", client("dev"), now).is_ok());
+    }
+
+    #[test]
+    fn routes_without_a_rate_are_unlimited() {
+        let limiter = limiter(&[("random", "1/hour")]);
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(limiter.take("index", client("ci"), now).is_ok());
+        }
+    }
+}
@@ -0,0 +1,648 @@
+use super::Response;
+use access::{self, Logger};
+use actix::{Actor, Addr, Handler, Message, Syn, SyncArbiter, SyncContext};
+use actix_web::{http, App, AsyncResponder, FutureResponse, HttpRequest, HttpResponse, Json, Path};
+use allocate::Options;
+use bulk;
+use error::ApiError;
+use futures::{future, Future};
+use git_release_name::{normalize_name, Allocator, Constraints};
+use openapi::{self, Parameters, Schema};
+use param::Query;
+use rand;
+use rusqlite::{self, Connection, OptionalExtension, Row, TransactionBehavior};
+use serde_json::Value;
+use std::path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Names are unique ignoring case and separators, and an owner holds at
+/// most one name for each purpose.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS reservations (
+        key TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        sha TEXT NOT NULL,
+        owner TEXT NOT NULL,
+        purpose TEXT NOT NULL,
+        reserved_at INTEGER NOT NULL,
+        UNIQUE (owner, purpose)
+    )";
+
+const COLUMNS: &str = "name, sha, owner, purpose, reserved_at";
+
+/// How long to wait for another process that is writing to the store.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A name held by an owner for a purpose, such as a CI pipeline naming a
+/// preview environment.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct Reservation {
+    name: String,
+    sha: String,
+    owner: String,
+    purpose: String,
+    /// Seconds since the unix epoch
+    reserved_at: i64,
+}
+
+impl Reservation {
+    fn from_row(row: &Row) -> rusqlite::Result<Reservation> {
+        Ok(Reservation {
+            name: row.get(0)?,
+            sha: row.get(1)?,
+            owner: row.get(2)?,
+            purpose: row.get(3)?,
+            reserved_at: row.get(4)?,
+        })
+    }
+}
+
+impl Schema for Reservation {
+    fn schema() -> Value {
+        let string = openapi::string;
+        openapi::object(
+            &[
+                ("name", string()),
+                ("sha", string()),
+                ("owner", string()),
+                ("purpose", string()),
+                ("reserved_at", openapi::integer()),
+            ],
+            &[],
+        )
+    }
+}
+
+/// Why the store couldn't do what was asked: an error for the client, or a
+/// failure of the store itself, which is only described in the log.
+#[derive(Debug, Eq, PartialEq)]
+enum Failure {
+    Api(ApiError),
+    Storage(String),
+}
+
+impl From<ApiError> for Failure {
+    fn from(err: ApiError) -> Failure {
+        Failure::Api(err)
+    }
+}
+
+impl From<rusqlite::Error> for Failure {
+    fn from(err: rusqlite::Error) -> Failure {
+        Failure::Storage(err.to_string())
+    }
+}
+
+impl Failure {
+    /// The error for the client, logging what went wrong with the store.
+    fn report(self, log: &Logger) -> ApiError {
+        match self {
+            Failure::Api(err) => err,
+            Failure::Storage(err) => {
+                log.error(&format!("The reservation store failed: {}", err));
+                ApiError::storage()
+            }
+        }
+    }
+}
+
+/// Reservations kept in a SQLite file. The file can be shared by several
+/// servers on the same host, since every reservation happens in a single
+/// write transaction.
+///
+/// The store is an actor on a thread of its own, so a write waiting on
+/// another server only holds up other reservations rather than every
+/// request on a worker.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Actor for Store {
+    type Context = SyncContext<Self>;
+}
+
+impl Store {
+    /// Starts the store on its own thread. The store is opened once here so
+    /// that one which can't be opened stops the server from starting.
+    pub fn start(path: &path::Path) -> rusqlite::Result<Addr<Syn, Store>> {
+        Store::open(path)?;
+        let path = path.to_path_buf();
+        Ok(SyncArbiter::start(1, move || {
+            Store::open(&path).expect("the store has been opened before")
+        }))
+    }
+
+    fn open(path: &path::Path) -> rusqlite::Result<Store> {
+        Store::new(Connection::open(path)?)
+    }
+
+    fn new(conn: Connection) -> rusqlite::Result<Store> {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Store { conn })
+    }
+
+    /// Reserves a free name for the owner and purpose. When they already
+    /// hold a name it is returned instead, along with `false`, so retrying
+    /// a reservation is safe.
+    fn reserve(
+        &mut self,
+        owner: &str,
+        purpose: &str,
+        constraints: Constraints,
+        seed: u64,
+    ) -> Result<(Reservation, bool), Failure> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let existing = tx
+            .query_row(
+                &format!(
+                    "SELECT {} FROM reservations WHERE owner = ?1 AND purpose = ?2",
+                    COLUMNS
+                ),
+                [owner, purpose],
+                Reservation::from_row,
+            )
+            .optional()?;
+        if let Some(reservation) = existing {
+            return Ok((reservation, false));
+        }
+
+        let taken = tx
+            .prepare("SELECT name FROM reservations")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<String>>>()
+            })?;
+        let allocation = Allocator::new(taken, constraints, seed)
+            .next()
+            .ok_or_else(|| ApiError::no_free_names(1))?;
+        let reservation = Reservation {
+            name: allocation.phrase.to_string(),
+            sha: allocation.sha,
+            owner: owner.to_string(),
+            purpose: purpose.to_string(),
+            reserved_at: now(),
+        };
+        tx.execute(
+            "INSERT INTO reservations (key, name, sha, owner, purpose, reserved_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                normalize_name(&reservation.name),
+                reservation.name,
+                reservation.sha,
+                reservation.owner,
+                reservation.purpose,
+                reservation.reserved_at,
+            ],
+        )?;
+        tx.commit()?;
+        Ok((reservation, true))
+    }
+
+    /// Lists reservations, oldest first, optionally only those of an owner
+    /// or for a purpose.
+    fn list(
+        &self,
+        owner: Option<&str>,
+        purpose: Option<&str>,
+    ) -> Result<Vec<Reservation>, Failure> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM reservations
+             WHERE (?1 IS NULL OR owner = ?1) AND (?2 IS NULL OR purpose = ?2)
+             ORDER BY reserved_at, key",
+            COLUMNS
+        ))?;
+        let reservations = stmt
+            .query_map([owner, purpose], Reservation::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(reservations)
+    }
+
+    /// Finds the reservation of a name, compared ignoring case and
+    /// separators.
+    fn find(&self, name: &str) -> Result<Option<Reservation>, Failure> {
+        let found = self
+            .conn
+            .query_row(
+                &format!("SELECT {} FROM reservations WHERE key = ?1", COLUMNS),
+                [normalize_name(name)],
+                Reservation::from_row,
+            )
+            .optional()?;
+        Ok(found)
+    }
+
+    /// Releases a name held by the owner so it can be reserved again,
+    /// returning the reservation that held it. A name held by anyone else is
+    /// left alone.
+    fn release(&mut self, name: &str, owner: &str) -> Result<Option<Reservation>, Failure> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let key = normalize_name(name);
+        let holder = tx
+            .query_row(
+                "SELECT owner FROM reservations WHERE key = ?1",
+                [&key],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        match holder {
+            None => return Ok(None),
+            Some(ref holder) if holder != owner => return Err(ApiError::not_owner(name).into()),
+            Some(_) => {}
+        }
+        let reservation = tx.query_row(
+            &format!(
+                "DELETE FROM reservations WHERE key = ?1 RETURNING {}",
+                COLUMNS
+            ),
+            [&key],
+            Reservation::from_row,
+        )?;
+        tx.commit()?;
+        Ok(Some(reservation))
+    }
+}
+
+/// Reserves a name for an owner and purpose.
+struct Reserve {
+    owner: String,
+    purpose: String,
+    constraints: Constraints,
+    seed: u64,
+}
+
+impl Message for Reserve {
+    type Result = Result<(Reservation, bool), Failure>;
+}
+
+impl Handler<Reserve> for Store {
+    type Result = Result<(Reservation, bool), Failure>;
+
+    fn handle(&mut self, msg: Reserve, _: &mut SyncContext<Self>) -> Self::Result {
+        self.reserve(&msg.owner, &msg.purpose, msg.constraints, msg.seed)
+    }
+}
+
+/// Lists the reservations, optionally only those of an owner or for a
+/// purpose.
+struct List {
+    owner: Option<String>,
+    purpose: Option<String>,
+}
+
+impl Message for List {
+    type Result = Result<Vec<Reservation>, Failure>;
+}
+
+impl Handler<List> for Store {
+    type Result = Result<Vec<Reservation>, Failure>;
+
+    fn handle(&mut self, msg: List, _: &mut SyncContext<Self>) -> Self::Result {
+        self.list(msg.owner.as_deref(), msg.purpose.as_deref())
+    }
+}
+
+/// Finds the reservation of a name.
+struct Find(String);
+
+impl Message for Find {
+    type Result = Result<Option<Reservation>, Failure>;
+}
+
+impl Handler<Find> for Store {
+    type Result = Result<Option<Reservation>, Failure>;
+
+    fn handle(&mut self, Find(name): Find, _: &mut SyncContext<Self>) -> Self::Result {
+        self.find(&name)
+    }
+}
+
+/// Releases a name held by an owner.
+struct Release {
+    name: String,
+    owner: String,
+}
+
+impl Message for Release {
+    type Result = Result<Option<Reservation>, Failure>;
+}
+
+impl Handler<Release> for Store {
+    type Result = Result<Option<Reservation>, Failure>;
+
+    fn handle(&mut self, msg: Release, _: &mut SyncContext<Self>) -> Self::Result {
+        self.release(&msg.name, &msg.owner)
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[derive(Deserialize)]
+pub struct Body {
+    owner: String,
+    purpose: String,
+    #[serde(flatten)]
+    options: Options,
+}
+
+#[derive(Deserialize)]
+pub struct Params {
+    owner: Option<String>,
+    purpose: Option<String>,
+}
+
+/// Names can only be released by the owner holding them.
+#[derive(Deserialize)]
+pub struct ReleaseParams {
+    owner: String,
+}
+
+impl Schema for Body {
+    fn schema() -> Value {
+        let mut properties = vec![("owner", openapi::string()), ("purpose", openapi::string())];
+        let options = Options::properties();
+        let optional: Vec<&str> = options.iter().map(|&(name, _)| name).collect();
+        properties.extend(options);
+        openapi::object(&properties, &optional)
+    }
+}
+
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![
+            openapi::query("owner", "Only reservations of the owner", openapi::string()),
+            openapi::query(
+                "purpose",
+                "Only reservations for the purpose",
+                openapi::string(),
+            ),
+        ]
+    }
+}
+
+impl Parameters for ReleaseParams {
+    fn parameters() -> Vec<Value> {
+        vec![openapi::required_query(
+            "owner",
+            "The owner holding the name",
+            openapi::string(),
+        )]
+    }
+}
+
+/// Asks the store on behalf of a request, logging anything that went wrong
+/// with the store itself.
+fn ask<M, T>(store: &Addr<Syn, Store>, req: &HttpRequest, msg: M) -> FutureResponse<T>
+where
+    M: Message<Result = Result<T, Failure>> + Send + 'static,
+    T: Send + 'static,
+    Store: Handler<M>,
+{
+    let log = access::logger(req);
+    Box::new(store.send(msg).then(move |result| {
+        result
+            .unwrap_or_else(|err| Err(Failure::Storage(err.to_string())))
+            .map_err(|failure| failure.report(&log).into())
+    }))
+}
+
+fn create(
+    store: &Addr<Syn, Store>,
+    req: &HttpRequest,
+    body: &Body,
+) -> FutureResponse<HttpResponse> {
+    if body.owner.is_empty() || body.purpose.is_empty() {
+        let err = ApiError::invalid_body("owner and purpose may not be empty");
+        return Box::new(future::err(err.into()));
+    }
+    let constraints = match body.options.constraints() {
+        Ok(constraints) => constraints,
+        Err(err) => return Box::new(future::err(err.into())),
+    };
+    let reserve = Reserve {
+        owner: body.owner.clone(),
+        purpose: body.purpose.clone(),
+        constraints,
+        seed: body.options.seed.unwrap_or_else(rand::random),
+    };
+    ask(store, req, reserve)
+        .map(|(reservation, created)| {
+            let mut resp = if created {
+                HttpResponse::Created()
+            } else {
+                HttpResponse::Ok()
+            };
+            resp.json(Response::new(reservation))
+        })
+        .responder()
+}
+
+fn list(store: &Addr<Syn, Store>, req: &HttpRequest, q: &Params) -> FutureResponse<HttpResponse> {
+    let list = List {
+        owner: q.owner.clone(),
+        purpose: q.purpose.clone(),
+    };
+    ask(store, req, list)
+        .map(|reservations| HttpResponse::Ok().json(Response::new(reservations)))
+        .responder()
+}
+
+fn show(store: &Addr<Syn, Store>, req: &HttpRequest, name: String) -> FutureResponse<HttpResponse> {
+    ask(store, req, Find(name.clone()))
+        .and_then(move |found| match found {
+            Some(reservation) => Ok(HttpResponse::Ok().json(Response::new(reservation))),
+            None => Err(ApiError::unknown_reservation(&name).into()),
+        })
+        .responder()
+}
+
+fn release(
+    store: &Addr<Syn, Store>,
+    req: &HttpRequest,
+    name: String,
+    q: &ReleaseParams,
+) -> FutureResponse<HttpResponse> {
+    let release = Release {
+        name: name.clone(),
+        owner: q.owner.clone(),
+    };
+    ask(store, req, release)
+        .and_then(move |released| match released {
+            Some(reservation) => Ok(HttpResponse::Ok().json(Response::new(reservation))),
+            None => Err(ApiError::unknown_reservation(&name).into()),
+        })
+        .responder()
+}
+
+/// Adds the reservation routes to the app, backed by the store. Bodies are
+/// limited to `max_body` bytes.
+pub fn resources(app: App, store: Addr<Syn, Store>, max_body: usize) -> App {
+    let reservations = store.clone();
+    app.resource(openapi::path("reservations"), move |r| {
+        r.name("reservations");
+        let store = reservations.clone();
+        r.method(http::Method::GET)
+            .with2(move |req: HttpRequest, q: Query<Params>| list(&store, &req, &q));
+        r.method(http::Method::POST)
+            .with2(move |req: HttpRequest, body: Json<Body>| create(&reservations, &req, &body))
+            .1
+            .limit(max_body)
+            .error_handler(bulk::body_error);
+    })
+    .resource(openapi::path("reservation"), move |r| {
+        r.name("reservation");
+        let shown = store.clone();
+        r.method(http::Method::GET)
+            .with2(move |req: HttpRequest, name: Path<String>| {
+                show(&shown, &req, name.into_inner())
+            });
+        r.method(http::Method::DELETE).with3(
+            move |req: HttpRequest, name: Path<String>, q: Query<ReleaseParams>| {
+                release(&store, &req, name.into_inner(), &q)
+            },
+        );
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::TestRequest;
+    use git_release_name::Case;
+    use testing::{fetch, server};
+
+    fn store() -> Store {
+        Store::new(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn names_are_reserved_once() {
+        let mut store = store();
+        let (first, created) = store
+            .reserve("ci", "preview-1", Constraints::default(), 0)
+            .unwrap();
+        assert!(created);
+        assert_eq!(first.name, "forcibly endways holdback");
+
+        let (second, _) = store
+            .reserve("ci", "preview-2", Constraints::default(), 0)
+            .unwrap();
+        assert_eq!(second.name, "unpeacefully costly protest");
+    }
+
+    #[test]
+    fn reserving_again_returns_the_same_name() {
+        let mut store = store();
+        let (first, _) = store
+            .reserve("ci", "preview", Constraints::default(), 0)
+            .unwrap();
+        let (again, created) = store
+            .reserve("ci", "preview", Constraints::default(), 1)
+            .unwrap();
+        assert!(!created);
+        assert_eq!(again, first);
+    }
+
+    #[test]
+    fn names_are_found_ignoring_case() {
+        let mut store = store();
+        let constraints = Constraints {
+            case: Case::Kebab,
+            ..Constraints::default()
+        };
+        store.reserve("ci", "preview", constraints, 0).unwrap();
+        let found = store.find("Forcibly Endways Holdback").unwrap().unwrap();
+        assert_eq!(found.name, "forcibly-endways-holdback");
+        assert_eq!(found.owner, "ci");
+    }
+
+    #[test]
+    fn reservations_can_be_listed_and_released() {
+        let mut store = store();
+        store.reserve("ci", "a", Constraints::default(), 0).unwrap();
+        store.reserve("qa", "a", Constraints::default(), 0).unwrap();
+        assert_eq!(store.list(None, None).unwrap().len(), 2);
+        assert_eq!(store.list(Some("qa"), None).unwrap().len(), 1);
+        assert_eq!(store.list(None, Some("b")).unwrap().len(), 0);
+
+        let released = store.release("forcibly endways holdback", "ci").unwrap();
+        assert_eq!(released.unwrap().owner, "ci");
+        assert_eq!(
+            store.release("forcibly endways holdback", "ci").unwrap(),
+            None
+        );
+        assert_eq!(store.list(None, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn only_the_owner_can_release_a_name() {
+        let mut store = store();
+        store.reserve("ci", "a", Constraints::default(), 0).unwrap();
+        assert_eq!(
+            store.release("forcibly endways holdback", "qa"),
+            Err(Failure::Api(ApiError::not_owner(
+                "forcibly endways holdback"
+            )))
+        );
+        assert_eq!(store.list(None, None).unwrap().len(), 1);
+        assert_eq!(store.release("unreserved name", "qa").unwrap(), None);
+    }
+
+    #[test]
+    fn releasing_over_http_requires_the_owner() {
+        let mut srv = server();
+        let body = r#"{"owner": "ci", "purpose": "pr-1", "seed": 0}"#;
+        assert_eq!(
+            fetch(&mut srv, Method::POST, "/api/reservations", body).0,
+            201
+        );
+
+        let path = "/api/reservations/forcibly%20endways%20holdback";
+        let (status, body) = fetch(&mut srv, Method::DELETE, path, "");
+        assert_eq!(status, 400, "{}", body);
+        let (status, body) = fetch(&mut srv, Method::DELETE, &format!("{}?owner=qa", path), "");
+        assert_eq!(status, 403);
+        assert!(body.contains("not_owner"));
+        let (status, _) = fetch(&mut srv, Method::DELETE, &format!("{}?owner=ci", path), "");
+        assert_eq!(status, 200);
+        let (status, _) = fetch(&mut srv, Method::DELETE, &format!("{}?owner=ci", path), "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn storage_failures_are_only_logged() {
+        let store = store();
+        store.conn.execute_batch("DROP TABLE reservations").unwrap();
+        let failure = store.list(None, None).unwrap_err();
+        match failure {
+            Failure::Storage(ref err) => assert!(err.contains("no such table"), "{}", err),
+            ref other => panic!("unexpected {:?}", other),
+        }
+        let log = access::logger(&TestRequest::default().finish());
+        let err = failure.report(&log);
+        assert_eq!(err, ApiError::storage());
+        let body = ::serde_json::to_string(&err).unwrap();
+        assert!(!body.contains("no such table"), "{}", body);
+    }
+
+    #[test]
+    fn bodies_accept_the_allocate_options() {
+        let body: Body = ::serde_json::from_str(
+            r#"{"owner": "ci", "purpose": "pr-7", "format": "snake", "initial": "u"}"#,
+        )
+        .unwrap();
+        let constraints = body.options.constraints().unwrap();
+        assert_eq!(constraints.case, Case::Snake);
+        assert_eq!(constraints.initial, Some('u'));
+    }
+}
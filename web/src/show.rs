@@ -1,16 +1,97 @@
-use actix_web::{http::StatusCode, HttpResponse, Path, Query};
-use git_release_name::{self, Case};
-use param::Format;
+use super::{Name, Response};
+use access;
+use actix_web::{HttpRequest, HttpResponse, Path};
+use cache::Etag;
+use error::ApiError;
+use metrics;
+use openapi::{self, Parameters};
+use output::{Output, Row};
+use param::{self, Format, Query};
+use serde_json::Value;
 
 #[derive(Deserialize)]
 pub struct Params {
-    format: Option<Format>,
+    format: Option<String>,
+    version: Option<String>,
+    output: Option<String>,
 }
 
-pub fn handler(info: Path<String>, q: Query<Params>) -> HttpResponse {
-    let format = q.format.unwrap_or(Case::Lower.into());
-    match git_release_name::lookup(&info.into_inner()) {
-        Ok(name) => HttpResponse::build(StatusCode::OK).body(name.with_case(*format).to_string()),
-        Err(_) => HttpResponse::build(StatusCode::NOT_FOUND).finish(),
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![openapi::format(), openapi::version(), openapi::output()]
+    }
+}
+
+pub fn handler(
+    mut req: HttpRequest,
+    info: Path<String>,
+    q: Query<Params>,
+) -> Result<HttpResponse, ApiError> {
+    let format = Format::from_param(q.format.as_deref())?;
+    param::version(q.version.as_deref())?;
+    let output = Output::negotiate(&req, q.output.as_deref(), Output::Text)?;
+    let sha = param::sha(&info)?;
+    access::shas(&mut req, 1);
+    let name = metrics::lookup(sha)
+        .map(|name| name.with_case(*format).to_string())
+        .map_err(|err| ApiError::lookup(sha, err))?;
+
+    // Only a sha that can be named has a representation to cache.
+    let etag = Etag::new(&[&sha], *format, output);
+    if etag.matches(&req) {
+        return Ok(etag.not_modified());
+    }
+
+    let rows = [Row::new(sha, Some(&name))];
+    let resp = output.respond(
+        &Response::new(Name::new(name.clone(), sha.to_string())),
+        &rows,
+    );
+    Ok(etag.apply(resp))
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::http::{header, Method};
+    use testing::{send, server};
+
+    #[test]
+    fn only_named_shas_are_cached() {
+        let mut srv = server();
+        for &tag in &["*", "\"anything\""] {
+            let req = srv
+                .client(Method::GET, "/api/release-name/xyz")
+                .header(header::IF_NONE_MATCH, tag)
+                .finish()
+                .unwrap();
+            let (status, headers, _) = send(&mut srv, req);
+            assert_eq!(status, 400);
+            assert!(headers.get(header::ETAG).is_none());
+            assert_ne!(
+                headers
+                    .get(header::CACHE_CONTROL)
+                    .map(|v| v.to_str().unwrap()),
+                Some("public, max-age=31536000, immutable")
+            );
+        }
+
+        let req = srv
+            .client(Method::GET, "/api/release-name/abc")
+            .finish()
+            .unwrap();
+        let (status, headers, _) = send(&mut srv, req);
+        assert_eq!(status, 200);
+        let req = srv
+            .client(Method::GET, "/api/release-name/abc")
+            .header(header::IF_NONE_MATCH, headers[header::ETAG].clone())
+            .finish()
+            .unwrap();
+        assert_eq!(send(&mut srv, req).0, 304);
+        let req = srv
+            .client(Method::GET, "/api/release-name/abc")
+            .header(header::IF_NONE_MATCH, "*")
+            .finish()
+            .unwrap();
+        assert_eq!(send(&mut srv, req).0, 304);
     }
 }
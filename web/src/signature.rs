@@ -0,0 +1,51 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Decodes a hex signature.
+pub fn hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Checks a hex HMAC-SHA256 signature of the parts, which are signed as if
+/// they were one message.
+pub fn hmac_sha256(secret: &str, parts: &[&[u8]], signature: &str) -> bool {
+    hex(signature).is_some_and(|signature| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("hmac accepts keys of any length");
+        for part in parts {
+            mac.update(part);
+        }
+        mac.verify_slice(&signature).is_ok()
+    })
+}
+
+/// Compares secrets without leaking how much of them matched.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn signatures_must_be_hex() {
+        assert_eq!(hex("0aff"), Some(vec![0x0a, 0xff]));
+        assert_eq!(hex("0af"), None);
+        assert_eq!(hex("zz"), None);
+        assert!(!hmac_sha256("secret", &[b"body"], "not hex"));
+    }
+
+    #[test]
+    fn secrets_are_compared_exactly() {
+        assert!(constant_time_eq(b"hunter2", b"hunter2"));
+        assert!(!constant_time_eq(b"hunter2", b"hunter3"));
+        assert!(!constant_time_eq(b"hunter2", b"hunter"));
+    }
+}
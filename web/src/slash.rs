@@ -0,0 +1,329 @@
+use access;
+use actix_web::error::PayloadError;
+use actix_web::http::HeaderMap;
+use actix_web::{AsyncResponder, Error, FutureResponse, HttpMessage, HttpRequest, HttpResponse};
+use error::ApiError;
+use futures::Future;
+use git_release_name::{self, RandomShas};
+use limits::Limits;
+use metrics;
+use openapi::{self, Schema};
+use rand;
+use serde_json::Value;
+use serde_urlencoded;
+use signature;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many seconds a signed Slack request may be off from the clock before
+/// it is treated as a replay.
+const MAX_SKEW: u64 = 300;
+
+/// The fields of a slash command request that are used. Slack and
+/// Mattermost send many more.
+#[derive(Deserialize)]
+pub struct Command {
+    #[serde(default)]
+    command: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    token: String,
+}
+
+impl Schema for Command {
+    fn schema() -> Value {
+        let string = openapi::string;
+        openapi::object(
+            &[
+                ("command", string()),
+                ("text", string()),
+                ("token", string()),
+            ],
+            &["command", "text", "token"],
+        )
+    }
+}
+
+/// A message for the channel. Ephemeral ones are only shown to the person
+/// who ran the command.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+struct Reply {
+    response_type: &'static str,
+    text: String,
+}
+
+impl Reply {
+    fn in_channel(text: String) -> Reply {
+        Reply {
+            response_type: "in_channel",
+            text,
+        }
+    }
+
+    fn ephemeral(text: String) -> Reply {
+        Reply {
+            response_type: "ephemeral",
+            text,
+        }
+    }
+}
+
+fn usage(command: &str) -> Reply {
+    Reply::ephemeral(format!(
+        "Usage: `{0} <sha>`, `{0} reverse <name>` or `{0} random`",
+        command
+    ))
+}
+
+/// Answers the text of a command, along with the number of shas named.
+/// Shas are held to the same limits as a query string.
+fn reply(command: &str, text: &str, limits: &Limits) -> (Reply, usize) {
+    let command = if command.is_empty() {
+        "/releasename"
+    } else {
+        command
+    };
+    let mut words = text.split_whitespace();
+    match words.next() {
+        None | Some("help") => (usage(command), 0),
+        Some("random") => {
+            let sha = RandomShas::new(rand::random())
+                .next()
+                .expect("random shas never run out");
+            (name(&[sha]), 1)
+        }
+        Some("reverse") => {
+            let name = words.collect::<Vec<&str>>().join(" ");
+            if name.is_empty() {
+                return (usage(command), 0);
+            }
+            let shas: Vec<String> = git_release_name::reverse(&name)
+                .iter()
+                .map(|sha| format!("`{}`", sha))
+                .collect();
+            if shas.is_empty() {
+                (
+                    Reply::ephemeral(format!("*{}* isn't a release name", name)),
+                    0,
+                )
+            } else {
+                let text = format!("*{}* is {}", name, shas.join(" or "));
+                (Reply::in_channel(text), 0)
+            }
+        }
+        Some(_) => match limits.shas(text.split_whitespace(), limits.shas) {
+            Ok(shas) => (name(&shas), shas.len()),
+            Err(err) => (Reply::ephemeral(err.to_string()), 0),
+        },
+    }
+}
+
+/// Names every sha, one per line.
+fn name<S: AsRef<str>>(shas: &[S]) -> Reply {
+    let lines: Result<Vec<String>, String> = shas
+        .iter()
+        .map(AsRef::as_ref)
+        .map(|sha| match metrics::lookup(sha) {
+            Ok(phrase) => Ok(format!("`{}` is *{}*", sha, phrase)),
+            Err(_) => Err(format!("`{}` isn't a sha", sha)),
+        })
+        .collect();
+    match lines {
+        Ok(lines) => Reply::in_channel(lines.join("\n")),
+        Err(text) => Reply::ephemeral(text),
+    }
+}
+
+/// Answers Slack and Mattermost slash commands. Requests are checked
+/// against the shared secret before anything else is done with them.
+#[derive(Clone)]
+pub struct Slash {
+    secret: String,
+}
+
+impl Slash {
+    pub fn new(secret: String) -> Slash {
+        Slash { secret }
+    }
+
+    /// Slack signs requests with its signing secret, while Mattermost sends
+    /// its token in the body and the `Authorization` header.
+    fn verify(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        command: &Command,
+        now: u64,
+    ) -> Result<(), ApiError> {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let verified = match header("x-slack-signature") {
+            Some(sig) => header("x-slack-request-timestamp")
+                .filter(|ts| {
+                    ts.parse::<u64>()
+                        .is_ok_and(|ts| ts.abs_diff(now) <= MAX_SKEW)
+                })
+                .zip(sig.strip_prefix("v0="))
+                .is_some_and(|(ts, sig)| {
+                    let prefix = format!("v0:{}:", ts);
+                    signature::hmac_sha256(&self.secret, &[prefix.as_bytes(), body], sig)
+                }),
+            None => {
+                let token = header("authorization")
+                    .and_then(|auth| auth.strip_prefix("Token "))
+                    .unwrap_or(&command.token);
+                signature::constant_time_eq(token.as_bytes(), self.secret.as_bytes())
+            }
+        };
+        if verified {
+            Ok(())
+        } else {
+            Err(ApiError::invalid_signature())
+        }
+    }
+
+    fn respond(&self, req: &mut HttpRequest, body: &[u8]) -> Result<HttpResponse, ApiError> {
+        let command: Command = serde_urlencoded::from_bytes(body)
+            .map_err(|e| ApiError::invalid_body(&e.to_string()))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.verify(req.headers(), body, &command, now)?;
+
+        let (reply, shas) = reply(&command.command, &command.text, &Limits::of(req));
+        access::shas(req, shas);
+        Ok(HttpResponse::Ok().json(reply))
+    }
+}
+
+pub fn handler(slash: &Slash, req: HttpRequest) -> FutureResponse<HttpResponse> {
+    let slash = slash.clone();
+    let mut named = req.clone();
+    let limit = Limits::of(&req).body;
+    req.body()
+        .limit(limit)
+        .map_err(move |err| match err {
+            PayloadError::Overflow => ApiError::body_too_large(limit),
+            err => ApiError::invalid_body(&err.to_string()),
+        })
+        .and_then(move |body| slash.respond(&mut named, &body))
+        .map_err(Error::from)
+        .responder()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    // The example from Slack's documentation on verifying requests.
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: u64 = 1_531_420_618;
+    const BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&\
+                        team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&\
+                        user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&\
+                        response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F\
+                        397700885554%2F96rGlfmibIGlgcZRskXaIFfN&\
+                        trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+    fn command(body: &str) -> Command {
+        serde_urlencoded::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn slack_signatures_are_verified() {
+        let slash = Slash::new(String::from(SECRET));
+        let req = TestRequest::with_header("X-Slack-Signature", SIGNATURE)
+            .header("X-Slack-Request-Timestamp", TIMESTAMP.to_string())
+            .finish();
+        let body = BODY.as_bytes();
+        assert!(slash
+            .verify(req.headers(), body, &command(BODY), TIMESTAMP + 10)
+            .is_ok());
+        assert_eq!(
+            slash.verify(
+                req.headers(),
+                body,
+                &command(BODY),
+                TIMESTAMP + MAX_SKEW + 1
+            ),
+            Err(ApiError::invalid_signature())
+        );
+        assert!(slash
+            .verify(req.headers(), b"text=tampered", &command(BODY), TIMESTAMP)
+            .is_err());
+    }
+
+    #[test]
+    fn mattermost_tokens_are_verified() {
+        let slash = Slash::new(String::from("xyzz0WbapA4vBCDEFasx0q6G"));
+        let unsigned = TestRequest::default().finish();
+        assert!(slash
+            .verify(unsigned.headers(), b"", &command(BODY), 0)
+            .is_ok());
+        let req = TestRequest::with_header("Authorization", "Token nope").finish();
+        assert!(slash.verify(req.headers(), b"", &command(BODY), 0).is_err());
+        assert!(slash
+            .verify(unsigned.headers(), b"", &command("text=abc"), 0)
+            .is_err());
+    }
+
+    #[test]
+    fn shas_are_named() {
+        let (answer, shas) = reply("/releasename", "abc123", &Limits::default());
+        assert_eq!(
+            answer,
+            Reply::in_channel(String::from("`abc123` is *proximally kilted rancor*"))
+        );
+        assert_eq!(shas, 1);
+        let (answer, _) = reply("/releasename", "abc xyz", &Limits::default());
+        assert_eq!(answer, Reply::ephemeral(String::from("`xyz` isn't a sha")));
+    }
+
+    #[test]
+    fn shas_are_held_to_the_limits() {
+        let limits = Limits {
+            shas: 2,
+            ..Limits::default()
+        };
+        let (answer, shas) = reply("/rn", "abc 123 def", &limits);
+        assert_eq!(answer.response_type, "ephemeral");
+        assert_eq!(shas, 0);
+        let (answer, _) = reply("/rn", "abcdefg\u{e9}", &limits);
+        assert_eq!(
+            answer,
+            Reply::ephemeral(ApiError::non_hexadecimal("abcdefg\u{e9}").to_string())
+        );
+    }
+
+    #[test]
+    fn names_can_be_reversed() {
+        let (answer, _) = reply(
+            "/rn",
+            "reverse intentionally mirky swineherds",
+            &Limits::default(),
+        );
+        assert_eq!(
+            answer,
+            Reply::in_channel(String::from(
+                "*intentionally mirky swineherds* is `a9677113`"
+            ))
+        );
+        let (answer, _) = reply("/rn", "reverse nothing at all", &Limits::default());
+        assert_eq!(answer.response_type, "ephemeral");
+    }
+
+    #[test]
+    fn random_names_and_help() {
+        let (answer, shas) = reply("/rn", "random", &Limits::default());
+        assert_eq!(answer.response_type, "in_channel");
+        assert_eq!(shas, 1);
+        let (help, _) = reply("/rn", "", &Limits::default());
+        assert_eq!(
+            help,
+            Reply::ephemeral(String::from(
+                "Usage: `/rn <sha>`, `/rn reverse <name>` or `/rn random`"
+            ))
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use super::Response;
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, Json};
+use git_release_name::{self, lookup_hashed, stats, Kind, DICTIONARY_VERSION};
+use openapi::{self, Schema};
+use serde_json::Value;
+
+/// The commit the server was built from, when given at compile time.
+const COMMIT: Option<&str> = option_env!("RELEASE_NAME_COMMIT");
+
+/// The versions of the crates that make up the service.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Crates {
+    web: &'static str,
+    dictionary: &'static str,
+}
+
+/// The number of words of each kind in the dictionary.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Words {
+    adverbs: usize,
+    adjectives: usize,
+    nouns: usize,
+}
+
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Version {
+    /// The release name of the service itself
+    name: String,
+    /// The commit the name was formed from, if known
+    commit: Option<&'static str>,
+    crates: Crates,
+    dictionary_version: u32,
+    words: Words,
+}
+
+impl Version {
+    fn new(commit: Option<&'static str>) -> Self {
+        let count = |kind| {
+            stats::slots()
+                .into_iter()
+                .find(|slot| slot.kind == kind)
+                .map_or(0, |slot| slot.words)
+        };
+        Version {
+            name: release_name(commit),
+            commit,
+            crates: Crates {
+                web: env!("CARGO_PKG_VERSION"),
+                dictionary: git_release_name::VERSION,
+            },
+            dictionary_version: DICTIONARY_VERSION,
+            words: Words {
+                adverbs: count(Kind::Adv),
+                adjectives: count(Kind::Adj),
+                nouns: count(Kind::Noun),
+            },
+        }
+    }
+}
+
+impl Schema for Version {
+    fn schema() -> Value {
+        let integer = openapi::integer;
+        openapi::object(
+            &[
+                ("name", openapi::string()),
+                ("commit", openapi::nullable(openapi::string())),
+                (
+                    "crates",
+                    openapi::object(
+                        &[
+                            ("web", openapi::string()),
+                            ("dictionary", openapi::string()),
+                        ],
+                        &[],
+                    ),
+                ),
+                ("dictionary_version", integer()),
+                (
+                    "words",
+                    openapi::object(
+                        &[
+                            ("adverbs", integer()),
+                            ("adjectives", integer()),
+                            ("nouns", integer()),
+                        ],
+                        &[],
+                    ),
+                ),
+            ],
+            &[],
+        )
+    }
+}
+
+/// Names the commit the service was built from. Without one the crate
+/// version is hashed so every release still gets its own name.
+fn release_name(commit: Option<&str>) -> String {
+    commit
+        .and_then(|sha| git_release_name::lookup(sha).ok())
+        .unwrap_or_else(|| lookup_hashed(env!("CARGO_PKG_VERSION")))
+        .to_string()
+}
+
+/// The process is up and answering requests.
+pub fn healthz(_: HttpRequest) -> &'static str {
+    "ok"
+}
+
+/// The dictionary is loaded and names can be looked up.
+pub fn readyz(_: HttpRequest) -> HttpResponse {
+    match git_release_name::lookup("0") {
+        Ok(_) => HttpResponse::build(StatusCode::OK).body("ok"),
+        Err(_) => {
+            HttpResponse::build(StatusCode::SERVICE_UNAVAILABLE).body("dictionary unavailable")
+        }
+    }
+}
+
+pub fn version(_: HttpRequest) -> Json<Response<Version>> {
+    Json(Response::new(Version::new(COMMIT)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn versions_describe_the_dictionary() {
+        let version = serde_json::to_value(Version::new(Some("abc"))).unwrap();
+        assert_eq!(version["name"], "ambitiously timeless gemot");
+        assert_eq!(version["commit"], "abc");
+        assert_eq!(version["dictionary_version"], DICTIONARY_VERSION);
+        assert_eq!(version["crates"]["dictionary"], git_release_name::VERSION);
+        assert_eq!(
+            version["words"]["adjectives"],
+            git_release_name::list(Kind::Adj).len()
+        );
+    }
+
+    #[test]
+    fn unknown_commits_fall_back_to_the_crate_version() {
+        assert_eq!(
+            release_name(None),
+            lookup_hashed(env!("CARGO_PKG_VERSION")).to_string()
+        );
+        assert_eq!(release_name(Some("xyz")), release_name(None));
+    }
+}
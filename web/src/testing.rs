@@ -0,0 +1,55 @@
+//! A server with every service enabled for tests that go through the whole
+//! app, middleware included.
+
+use access::{AccessLog, Level, LogFormat};
+use actix_web::client::ClientRequest;
+use actix_web::http::{HeaderMap, Method};
+use actix_web::test::TestServer;
+use actix_web::HttpMessage;
+use auth::Auth;
+use limits::Limits;
+use ratelimit::RateLimiter;
+use reservation::Store;
+use slash::Slash;
+use std::path::Path;
+use webhook::Webhook;
+use {app, Services};
+
+/// The secret webhooks and slash commands are signed with.
+pub const SECRET: &str = "secret";
+
+pub fn server() -> TestServer {
+    TestServer::with_factory(|| {
+        app(
+            AccessLog::new(LogFormat::Logfmt, Level::Off),
+            Services {
+                limits: Limits::default(),
+                auth: Auth::default(),
+                rates: RateLimiter::default(),
+                reservations: Some(Store::start(Path::new(":memory:")).unwrap()),
+                webhook: Some(Webhook::new(String::from(SECRET), None)),
+                slash: Some(Slash::new(String::from(SECRET))),
+            },
+        )
+    })
+}
+
+/// The status, headers and body of a response, byte for byte.
+pub fn send(srv: &mut TestServer, req: ClientRequest) -> (u16, HeaderMap, String) {
+    let resp = srv.execute(req.send()).unwrap();
+    let status = resp.status().as_u16();
+    let headers = resp.headers().clone();
+    let body = srv.execute(resp.body()).unwrap();
+    (status, headers, String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// The status and body of a request with a JSON body.
+pub fn fetch(srv: &mut TestServer, method: Method, path: &str, body: &str) -> (u16, String) {
+    let req = srv
+        .client(method, path)
+        .content_type("application/json")
+        .body(body.to_string())
+        .unwrap();
+    let (status, _, body) = send(srv, req);
+    (status, body)
+}
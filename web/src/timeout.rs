@@ -0,0 +1,122 @@
+use actix::Arbiter;
+use actix_web::error::{Error, ErrorRequestTimeout};
+use actix_web::server::{HttpHandler, HttpHandlerTask, IntoHttpHandler, ServerSettings, Writer};
+use actix_web::HttpRequest;
+use futures::{Async, Future, Poll};
+use std::iter::{self, Once};
+use std::time::Duration;
+use tokio_core::reactor::Timeout;
+
+/// Limits how long a request may take once its head has arrived, from
+/// reading the body to sending the response. A request that runs out of
+/// time has its connection closed, since the response may already be
+/// partly written. Slow heads are already cut off by the keep-alive timer.
+pub struct RequestTimeout<H> {
+    handler: H,
+    timeout: Option<Duration>,
+}
+
+impl<H> RequestTimeout<H> {
+    pub fn new(handler: H, timeout: Option<Duration>) -> RequestTimeout<H> {
+        RequestTimeout { handler, timeout }
+    }
+}
+
+/// Servers are built from any number of apps, and this is one.
+impl<H: IntoHttpHandler> IntoIterator for RequestTimeout<H> {
+    type Item = RequestTimeout<H>;
+    type IntoIter = Once<RequestTimeout<H>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        iter::once(self)
+    }
+}
+
+impl<H: IntoHttpHandler> IntoHttpHandler for RequestTimeout<H> {
+    type Handler = TimedHandler<H::Handler>;
+
+    fn into_handler(self, settings: ServerSettings) -> Self::Handler {
+        TimedHandler {
+            handler: self.handler.into_handler(settings),
+            timeout: self.timeout,
+        }
+    }
+}
+
+pub struct TimedHandler<H> {
+    handler: H,
+    timeout: Option<Duration>,
+}
+
+impl<H: HttpHandler> HttpHandler for TimedHandler<H> {
+    fn handle(&mut self, req: HttpRequest) -> Result<Box<dyn HttpHandlerTask>, HttpRequest> {
+        let task = self.handler.handle(req)?;
+        match self.timeout {
+            Some(timeout) => Ok(Box::new(TimedTask {
+                task,
+                timer: Timeout::new(timeout, Arbiter::handle()).expect("timers can be created"),
+            })),
+            None => Ok(task),
+        }
+    }
+}
+
+struct TimedTask {
+    task: Box<dyn HttpHandlerTask>,
+    timer: Timeout,
+}
+
+impl TimedTask {
+    /// Fails once the request has run out of time, and otherwise makes sure
+    /// the task is woken when it does.
+    fn check(&mut self) -> Result<(), Error> {
+        match self.timer.poll() {
+            Ok(Async::NotReady) => Ok(()),
+            _ => Err(ErrorRequestTimeout("the request took too long")),
+        }
+    }
+}
+
+impl HttpHandlerTask for TimedTask {
+    fn poll(&mut self) -> Poll<(), Error> {
+        self.check()?;
+        self.task.poll()
+    }
+
+    fn poll_io(&mut self, io: &mut dyn Writer) -> Poll<bool, Error> {
+        self.check()?;
+        self.task.poll_io(io)
+    }
+
+    fn disconnected(&mut self) {
+        self.task.disconnected()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestServer;
+    use actix_web::{http::Method, App, HttpResponse};
+    use futures::future;
+
+    fn server() -> TestServer {
+        TestServer::with_factory(|| {
+            let app = App::new()
+                .resource("/fast", |r| r.f(|_| HttpResponse::Ok()))
+                .resource("/slow", |r| {
+                    r.route().a(|_| future::empty::<HttpResponse, Error>())
+                });
+            RequestTimeout::new(app, Some(Duration::from_millis(200)))
+        })
+    }
+
+    #[test]
+    fn slow_requests_are_cut_off() {
+        let mut srv = server();
+        let req = srv.client(Method::GET, "/fast").finish().unwrap();
+        assert_eq!(srv.execute(req.send()).unwrap().status().as_u16(), 200);
+        let req = srv.client(Method::GET, "/slow").finish().unwrap();
+        assert!(srv.execute(req.send()).is_err());
+    }
+}
@@ -0,0 +1,631 @@
+use access;
+use actix_web::error::{InternalError, JsonPayloadError, ResponseError};
+use actix_web::http::header::HeaderValue;
+use actix_web::http::StatusCode;
+use actix_web::{Error, HttpRequest, HttpResponse, Json};
+use bulk::payload_error;
+use cache::{self, Etag};
+use error::ApiError;
+use git_release_name::{Case, Kind, Phrase, RandomShas, DICTIONARY_VERSION};
+use limits::Limits;
+use metrics;
+use openapi::{self, Parameters, Schema};
+use param::{self, Format, CSV};
+use rand;
+use random::{MAX_COUNT, SEED};
+use serde_json::Value;
+
+/// Every v2 route starts with this, so errors raised before a route is
+/// reached can still be given the v2 schema.
+pub const PREFIX: &str = "/api/v2/";
+
+/// The words of a name, uncased, as they appear in the dictionary.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Words {
+    adverb: String,
+    adjective: String,
+    noun: String,
+}
+
+impl From<&Phrase> for Words {
+    fn from(phrase: &Phrase) -> Words {
+        Words {
+            adverb: phrase.adverb().to_string(),
+            adjective: phrase.adjective().to_string(),
+            noun: phrase.noun().to_string(),
+        }
+    }
+}
+
+impl Schema for Words {
+    fn schema() -> Value {
+        openapi::object(
+            &[
+                ("adverb", openapi::string()),
+                ("adjective", openapi::string()),
+                ("noun", openapi::string()),
+            ],
+            &[],
+        )
+    }
+}
+
+/// Where each word of a name is in the dictionary.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Indexes {
+    adverb: usize,
+    adjective: usize,
+    noun: usize,
+}
+
+impl From<&Phrase> for Indexes {
+    fn from(phrase: &Phrase) -> Indexes {
+        Indexes {
+            adverb: phrase.index(Kind::Adv),
+            adjective: phrase.index(Kind::Adj),
+            noun: phrase.index(Kind::Noun),
+        }
+    }
+}
+
+impl Schema for Indexes {
+    fn schema() -> Value {
+        openapi::object(
+            &[
+                ("adverb", openapi::integer()),
+                ("adjective", openapi::integer()),
+                ("noun", openapi::integer()),
+            ],
+            &[],
+        )
+    }
+}
+
+/// A sha and its name. Every field is always present, so a sha that can't
+/// be named has null words and says why in its errors.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Name {
+    sha: String,
+    name: Option<String>,
+    words: Option<Words>,
+    indexes: Option<Indexes>,
+    case: String,
+    dictionary_version: u32,
+    errors: Vec<ApiError>,
+}
+
+impl Name {
+    fn new(sha: &str, case: Case) -> Name {
+        match metrics::lookup(sha) {
+            Ok(phrase) => Name {
+                sha: sha.to_string(),
+                words: Some(Words::from(&phrase)),
+                indexes: Some(Indexes::from(&phrase)),
+                name: Some(phrase.with_case(case).to_string()),
+                case: case.to_string(),
+                dictionary_version: DICTIONARY_VERSION,
+                errors: Vec::new(),
+            },
+            Err(err) => Name {
+                sha: sha.to_string(),
+                name: None,
+                words: None,
+                indexes: None,
+                case: case.to_string(),
+                dictionary_version: DICTIONARY_VERSION,
+                errors: vec![ApiError::lookup(sha, err)],
+            },
+        }
+    }
+}
+
+impl Schema for Name {
+    fn schema() -> Value {
+        openapi::object(
+            &[
+                ("sha", openapi::string()),
+                ("name", openapi::nullable(openapi::string())),
+                ("words", openapi::nullable(Words::schema())),
+                ("indexes", openapi::nullable(Indexes::schema())),
+                ("case", Format::schema()),
+                ("dictionary_version", openapi::integer()),
+                ("errors", Vec::<ApiError>::schema()),
+            ],
+            &[],
+        )
+    }
+}
+
+/// The body of every v2 response. The errors hold every error in the
+/// response, those of the names included, so they are empty when every sha
+/// was named.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Names {
+    names: Vec<Name>,
+    errors: Vec<ApiError>,
+}
+
+impl Names {
+    fn new(names: Vec<Name>) -> Names {
+        let errors = names
+            .iter()
+            .flat_map(|name| name.errors.iter().cloned())
+            .collect();
+        Names { names, errors }
+    }
+
+    fn failed(err: ApiError) -> Names {
+        Names {
+            names: Vec::new(),
+            errors: vec![err],
+        }
+    }
+
+    fn respond(&self, status: StatusCode) -> HttpResponse {
+        HttpResponse::build(status).json(self)
+    }
+}
+
+impl Schema for Names {
+    fn schema() -> Value {
+        openapi::object(
+            &[
+                ("names", Vec::<Name>::schema()),
+                ("errors", Vec::<ApiError>::schema()),
+            ],
+            &[],
+        )
+    }
+}
+
+/// The response for an error, in the v2 schema on v2 routes and as it has
+/// always been on the others.
+pub fn error_response<S>(req: &HttpRequest<S>, err: &ApiError) -> HttpResponse {
+    if req.path().starts_with(PREFIX) {
+        Names::failed(err.clone()).respond(err.status())
+    } else {
+        err.error_response()
+    }
+}
+
+fn respond<S>(req: &HttpRequest<S>, result: Result<HttpResponse, ApiError>) -> HttpResponse {
+    result.unwrap_or_else(|err| error_response(req, &err))
+}
+
+fn case() -> Value {
+    openapi::query("case", "The case of the names", Format::schema())
+}
+
+#[derive(Deserialize)]
+pub struct Params {
+    case: Option<String>,
+    version: Option<String>,
+}
+
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![case(), openapi::version()]
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListParams {
+    shas: CSV,
+    case: Option<String>,
+    version: Option<String>,
+}
+
+impl Parameters for ListParams {
+    fn parameters() -> Vec<Value> {
+        vec![
+            openapi::required_query("shas", "Comma separated shas", CSV::schema()),
+            case(),
+            openapi::version(),
+        ]
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RandomParams {
+    case: Option<String>,
+    version: Option<String>,
+    count: Option<usize>,
+    seed: Option<u64>,
+}
+
+impl Parameters for RandomParams {
+    fn parameters() -> Vec<Value> {
+        vec![
+            case(),
+            openapi::version(),
+            openapi::query(
+                "count",
+                "How many names to generate",
+                json!({ "type": "integer", "minimum": 0, "maximum": MAX_COUNT }),
+            ),
+            openapi::query(
+                "seed",
+                "Generates the same names every time",
+                openapi::integer(),
+            ),
+        ]
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Body {
+    shas: Vec<String>,
+    case: Option<String>,
+    version: Option<u32>,
+}
+
+impl Schema for Body {
+    fn schema() -> Value {
+        openapi::object(
+            &[
+                ("shas", openapi::array(openapi::string())),
+                ("case", Format::schema()),
+                ("version", openapi::integer()),
+            ],
+            &["case", "version"],
+        )
+    }
+}
+
+/// Names the shas in order, tagged with everything that went into the
+/// names.
+fn names(shas: &[String], case: Case) -> Names {
+    Names::new(shas.iter().map(|sha| Name::new(sha, case)).collect())
+}
+
+/// A tag for a response naming the shas. It differs from the tags of v1
+/// responses, whose bodies differ.
+fn etag(case: Case, shas: &[String]) -> Etag {
+    let mut parts = vec![String::from("v2"), case.to_string()];
+    parts.extend(shas.iter().cloned());
+    Etag::from_parts(&parts)
+}
+
+/// Names a single sha. A sha that can't be named is answered with the
+/// status of its error.
+pub fn show(mut req: HttpRequest) -> HttpResponse {
+    let result = name_one(&mut req);
+    respond(&req, result)
+}
+
+fn name_one(req: &mut HttpRequest) -> Result<HttpResponse, ApiError> {
+    let q: Params = param::query(req)?;
+    let case = Format::from_param(q.case.as_deref())?;
+    param::version(q.version.as_deref())?;
+    let shas = [req.match_info().get("sha").unwrap_or("").to_string()];
+    access::shas(req, 1);
+
+    let names = names(&shas, *case);
+    if let Some(err) = names.errors.first() {
+        return Ok(names.respond(err.status()));
+    }
+    // Only a sha that can be named has a representation to cache.
+    let etag = etag(*case, &shas);
+    if etag.matches(req) {
+        return Ok(etag.not_modified());
+    }
+    Ok(etag.apply(names.respond(StatusCode::OK)))
+}
+
+/// Names the shas in the query string, in order.
+pub fn index(mut req: HttpRequest) -> HttpResponse {
+    let result = name_query(&mut req);
+    respond(&req, result)
+}
+
+fn name_query(req: &mut HttpRequest) -> Result<HttpResponse, ApiError> {
+    let q: ListParams = param::query(req)?;
+    let case = Format::from_param(q.case.as_deref())?;
+    param::version(q.version.as_deref())?;
+    let limits = Limits::of(req);
+    let shas = limits.shas(q.shas.iter().map(String::as_str), limits.shas)?;
+    metrics::bulk_shas("v2_names", shas.len());
+    access::shas(req, shas.len());
+
+    let etag = etag(*case, &shas);
+    if etag.matches(req) {
+        return Ok(etag.not_modified());
+    }
+    Ok(etag.apply(names(&shas, *case).respond(StatusCode::OK)))
+}
+
+/// Names the shas in the body, in order.
+pub fn bulk(mut req: HttpRequest, body: Json<Body>) -> HttpResponse {
+    let result = name_body(&mut req, &body);
+    respond(&req, result)
+}
+
+fn name_body(req: &mut HttpRequest, body: &Body) -> Result<HttpResponse, ApiError> {
+    let case = Format::from_param(body.case.as_deref())?;
+    param::version(body.version.map(|v| v.to_string()).as_deref())?;
+    let limits = Limits::of(req);
+    let shas = limits.shas(body.shas.iter().map(String::as_str), limits.bulk_shas)?;
+    metrics::bulk_shas("v2_names", shas.len());
+    access::shas(req, shas.len());
+    Ok(names(&shas, *case).respond(StatusCode::OK))
+}
+
+/// Generates names, one unless a count is given.
+pub fn random(mut req: HttpRequest) -> HttpResponse {
+    let result = generate(&mut req);
+    respond(&req, result)
+}
+
+fn generate(req: &mut HttpRequest) -> Result<HttpResponse, ApiError> {
+    let q: RandomParams = param::query(req)?;
+    let case = Format::from_param(q.case.as_deref())?;
+    param::version(q.version.as_deref())?;
+    let count = q.count.unwrap_or(1);
+    if count > MAX_COUNT {
+        return Err(ApiError::too_many_shas(count, MAX_COUNT));
+    }
+    access::shas(req, count);
+
+    // As with v1, random names are never cached.
+    let seed = q.seed.unwrap_or_else(rand::random);
+    let shas: Vec<String> = RandomShas::new(seed).take(count).collect();
+    let mut resp = names(&shas, *case).respond(StatusCode::OK);
+    resp.headers_mut().insert(
+        SEED,
+        HeaderValue::from_str(&seed.to_string()).expect("seeds are numeric"),
+    );
+    Ok(cache::no_store(resp))
+}
+
+/// Reports problems reading the body in the v2 schema.
+pub fn body_error(err: JsonPayloadError, req: HttpRequest) -> Error {
+    let err = payload_error(err, &req);
+    let resp = error_response(&req, &err);
+    InternalError::from_response(err, resp).into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::http::{header, Method};
+    use git_release_name;
+    use serde_json;
+    use testing::{fetch, send, server};
+
+    #[test]
+    fn names_carry_their_words_and_indexes() {
+        let phrase = git_release_name::lookup("abc123").unwrap();
+        assert_eq!(
+            serde_json::to_value(Name::new("abc123", Case::Kebab)).unwrap(),
+            json!({
+                "sha": "abc123",
+                "name": "proximally-kilted-rancor",
+                "words": { "adverb": "proximally", "adjective": "kilted", "noun": "rancor" },
+                "indexes": {
+                    "adverb": phrase.index(Kind::Adv),
+                    "adjective": phrase.index(Kind::Adj),
+                    "noun": phrase.index(Kind::Noun),
+                },
+                "case": "kebab",
+                "dictionary_version": DICTIONARY_VERSION,
+                "errors": [],
+            })
+        );
+    }
+
+    #[test]
+    fn every_error_is_collected() {
+        let names = names(&[String::from("xyz"), String::from("abc")], Case::Lower);
+        let error = ApiError::non_hexadecimal("xyz");
+        assert_eq!(names.errors, vec![error.clone()]);
+        assert_eq!(
+            serde_json::to_value(&names.names[0]).unwrap(),
+            json!({
+                "sha": "xyz",
+                "name": null,
+                "words": null,
+                "indexes": null,
+                "case": "lower",
+                "dictionary_version": DICTIONARY_VERSION,
+                "errors": [error],
+            })
+        );
+    }
+
+    #[test]
+    fn only_named_shas_are_cached() {
+        let mut srv = server();
+        // The tag the sha would have if it could be named.
+        let resp = etag(Case::Lower, &[String::from("xyz")]).not_modified();
+        let tag = resp.headers()[header::ETAG].to_str().unwrap();
+        for tag in &["*", tag] {
+            let req = srv
+                .client(Method::GET, "/api/v2/names/xyz?case=lower")
+                .header(header::IF_NONE_MATCH, *tag)
+                .finish()
+                .unwrap();
+            let (status, headers, body) = send(&mut srv, req);
+            assert_eq!(status, 400);
+            assert!(headers.get(header::ETAG).is_none());
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(body["errors"], json!([ApiError::non_hexadecimal("xyz")]));
+        }
+    }
+
+    /// v1 consumers can't change, so its responses are pinned byte for byte.
+    #[test]
+    fn v1_responses_are_unchanged() {
+        let mut srv = server();
+        let cases = [
+            (
+                Method::GET,
+                "/api/release-name/abc123",
+                "",
+                200,
+                "proximally kilted rancor",
+            ),
+            (
+                Method::GET,
+                "/api/release-name/abc123?output=json",
+                "",
+                200,
+                r#"{"data":{"name":"proximally kilted rancor","sha":"abc123"}}"#,
+            ),
+            (
+                Method::GET,
+                "/api/release-name?shas=abc123&format=snake",
+                "",
+                200,
+                r#"{"data":{"names":{"abc123":"proximally_kilted_rancor"}}}"#,
+            ),
+            (
+                Method::POST,
+                "/api/release-name",
+                r#"["abc123","xyz"]"#,
+                200,
+                "{\"data\":[{\"sha\":\"abc123\",\"name\":\"proximally kilted rancor\",\"error\":null},\
+                 {\"sha\":\"xyz\",\"name\":null,\"error\":{\"code\":\"non_hexadecimal\",\
+                 \"message\":\"Shas may only contain hexadecimal characters\",\"value\":\"xyz\"}}]}",
+            ),
+            (
+                Method::GET,
+                "/api/release-name/random?seed=0&format=kebab",
+                "",
+                200,
+                r#"{"data":{"name":"forcibly-endways-holdback","sha":"e220a839"}}"#,
+            ),
+            (
+                Method::GET,
+                "/api/release-name/xyz",
+                "",
+                400,
+                "{\"error\":{\"code\":\"non_hexadecimal\",\
+                 \"message\":\"Shas may only contain hexadecimal characters\",\"value\":\"xyz\"}}",
+            ),
+        ];
+        for &(ref method, path, body, status, expected) in &cases {
+            assert_eq!(
+                fetch(&mut srv, method.clone(), path, body),
+                (status, expected.to_string()),
+                "{} {}",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn v2_responses_follow_the_schema() {
+        let mut srv = server();
+        let phrase = git_release_name::lookup("abc123").unwrap();
+        let abc123 = json!({
+            "sha": "abc123",
+            "name": "proximally_kilted_rancor",
+            "words": { "adverb": "proximally", "adjective": "kilted", "noun": "rancor" },
+            "indexes": {
+                "adverb": phrase.index(Kind::Adv),
+                "adjective": phrase.index(Kind::Adj),
+                "noun": phrase.index(Kind::Noun),
+            },
+            "case": "snake",
+            "dictionary_version": DICTIONARY_VERSION,
+            "errors": [],
+        });
+        let xyz = ApiError::non_hexadecimal("xyz");
+        let cases = vec![
+            (
+                Method::GET,
+                "/api/v2/names/abc123?case=snake",
+                "",
+                200,
+                json!({ "names": [abc123], "errors": [] }),
+            ),
+            (
+                Method::GET,
+                "/api/v2/names?shas=abc123,abc123&case=snake",
+                "",
+                200,
+                json!({ "names": [abc123, abc123], "errors": [] }),
+            ),
+            (
+                Method::POST,
+                "/api/v2/names",
+                r#"{"shas":["abc123"],"case":"snake","version":1}"#,
+                200,
+                json!({ "names": [abc123], "errors": [] }),
+            ),
+            (
+                Method::GET,
+                "/api/v2/names/xyz",
+                "",
+                400,
+                json!({
+                    "names": [{
+                        "sha": "xyz",
+                        "name": null,
+                        "words": null,
+                        "indexes": null,
+                        "case": "lower",
+                        "dictionary_version": DICTIONARY_VERSION,
+                        "errors": [xyz],
+                    }],
+                    "errors": [xyz],
+                }),
+            ),
+            (
+                Method::GET,
+                "/api/v2/names?shas=abc123&case=shouty",
+                "",
+                400,
+                json!({ "names": [], "errors": [ApiError::unknown_format("shouty")] }),
+            ),
+            (
+                Method::POST,
+                "/api/v2/names",
+                "[",
+                400,
+                json!({
+                    "names": [],
+                    "errors": [{
+                        "code": "invalid_body",
+                        "message": "Json deserialize error: EOF while parsing a list at line 1 column 1",
+                    }],
+                }),
+            ),
+            (
+                Method::GET,
+                "/api/v2/nothing",
+                "",
+                404,
+                json!({ "names": [], "errors": [ApiError::not_found("/api/v2/nothing")] }),
+            ),
+        ];
+        for (method, path, body, status, expected) in cases {
+            let (actual_status, actual) = fetch(&mut srv, method.clone(), path, body);
+            assert_eq!(
+                (
+                    actual_status,
+                    serde_json::from_str::<Value>(&actual).unwrap()
+                ),
+                (status, expected),
+                "{} {}",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn random_names_are_always_a_list() {
+        let mut srv = server();
+        let (status, body) = fetch(&mut srv, Method::GET, "/api/v2/random?seed=0", "");
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body["names"][0]["sha"], "e220a839");
+        assert_eq!(body["names"][0]["name"], "forcibly endways holdback");
+        assert_eq!(body["names"].as_array().unwrap().len(), 1);
+        let (_, body) = fetch(&mut srv, Method::GET, "/api/v2/random?seed=0&count=3", "");
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["names"].as_array().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,419 @@
+use super::Response;
+use access::{self, Logger};
+use actix::Arbiter;
+use actix_web::client;
+use actix_web::error::PayloadError;
+use actix_web::http::HeaderMap;
+use actix_web::{AsyncResponder, Error, FutureResponse, HttpMessage, HttpRequest, HttpResponse};
+use error::ApiError;
+use futures::Future;
+use git_release_name::Case;
+use limits::Limits;
+use metrics;
+use openapi::{self, Parameters, Schema};
+use param::Format;
+use serde_json::{self, Value};
+use serde_urlencoded;
+use signature;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// How long to wait for the forwarding url to answer.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The sha git sends as `after` when a ref is deleted.
+const DELETED: &str = "0000000000000000000000000000000000000000";
+
+/// The forges webhooks are accepted from.
+#[derive(Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Source {
+    GitHub,
+    GitLab,
+}
+
+/// Works out which forge sent the webhook and which event it is.
+fn event(headers: &HeaderMap) -> Result<(Source, &str), ApiError> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some(event) = header("x-github-event") {
+        Ok((Source::GitHub, event))
+    } else if let Some(event) = header("x-gitlab-event") {
+        Ok((Source::GitLab, event))
+    } else {
+        Err(ApiError::unknown_event(None))
+    }
+}
+
+fn is_push(source: Source, event: &str) -> bool {
+    match source {
+        Source::GitHub => event == "push",
+        Source::GitLab => event == "Push Hook" || event == "Tag Push Hook",
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Params {
+    format: Option<String>,
+}
+
+impl Parameters for Params {
+    fn parameters() -> Vec<Value> {
+        vec![openapi::format()]
+    }
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    id: String,
+    #[serde(default)]
+    message: String,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    full_name: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Project {
+    path_with_namespace: String,
+}
+
+/// The parts of a GitHub or GitLab push payload that are named. Tag pushes
+/// are push payloads too, usually without any commits.
+#[derive(Deserialize)]
+struct Push {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    after: Option<String>,
+    #[serde(default)]
+    commits: Vec<Commit>,
+    head_commit: Option<Commit>,
+    repository: Option<Repository>,
+    project: Option<Project>,
+}
+
+impl Push {
+    fn repository(&self) -> String {
+        self.project
+            .as_ref()
+            .map(|p| p.path_with_namespace.clone())
+            .or_else(|| {
+                self.repository
+                    .as_ref()
+                    .and_then(|r| r.full_name.clone().or_else(|| r.name.clone()))
+            })
+            .unwrap_or_default()
+    }
+
+    /// The commits that landed, or the commit a tag points at when the push
+    /// didn't carry any.
+    fn commits(self) -> Vec<Commit> {
+        if !self.commits.is_empty() {
+            return self.commits;
+        }
+        if let Some(head) = self.head_commit {
+            return vec![head];
+        }
+        self.after
+            .filter(|after| after != DELETED)
+            .map(|id| Commit {
+                id,
+                message: String::new(),
+                url: None,
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// A pushed commit and its release name.
+#[derive(Serialize, Debug)]
+struct Named {
+    sha: String,
+    name: String,
+    /// The first line of the commit message
+    message: String,
+    url: Option<String>,
+}
+
+/// The names of everything in a push. `text` is a one line summary, which
+/// chat services that accept incoming webhooks will post as is.
+#[derive(Serialize, Debug)]
+pub struct Summary {
+    source: Source,
+    repository: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+    commits: Vec<Named>,
+    text: String,
+}
+
+impl Schema for Summary {
+    fn schema() -> Value {
+        let string = openapi::string;
+        let commit = openapi::object(
+            &[
+                ("sha", string()),
+                ("name", string()),
+                ("message", string()),
+                ("url", openapi::nullable(string())),
+            ],
+            &[],
+        );
+        openapi::object(
+            &[
+                ("source", openapi::string_enum(&["github", "gitlab"])),
+                ("repository", string()),
+                ("ref", string()),
+                ("commits", openapi::array(commit)),
+                ("text", string()),
+            ],
+            &[],
+        )
+    }
+}
+
+fn summarize(source: Source, push: Push, case: Case) -> Result<Summary, ApiError> {
+    let repository = push.repository();
+    let git_ref = push.git_ref.clone();
+    let commits = push
+        .commits()
+        .into_iter()
+        .map(|commit| {
+            let name = metrics::lookup(&commit.id)
+                .map_err(|e| ApiError::lookup(&commit.id, e))?
+                .with_case(case)
+                .to_string();
+            Ok(Named {
+                name,
+                message: commit.message.lines().next().unwrap_or("").to_string(),
+                url: commit.url,
+                sha: commit.id,
+            })
+        })
+        .collect::<Result<Vec<Named>, ApiError>>()?;
+
+    let short_ref = git_ref
+        .trim_start_matches("refs/heads/")
+        .trim_start_matches("refs/tags/");
+    let names: Vec<String> = commits
+        .iter()
+        .map(|c| format!("{} ({})", c.name, c.sha.get(..7).unwrap_or(&c.sha)))
+        .collect();
+    Ok(Summary {
+        text: format!("{} {}: {}", repository, short_ref, names.join(", ")),
+        source,
+        repository,
+        git_ref,
+        commits,
+    })
+}
+
+/// Names the commits of GitHub and GitLab pushes. Payloads are checked
+/// against the shared secret before anything else is done with them.
+#[derive(Clone)]
+pub struct Webhook {
+    secret: String,
+    forward: Option<String>,
+}
+
+impl Webhook {
+    pub fn new(secret: String, forward: Option<String>) -> Webhook {
+        Webhook { secret, forward }
+    }
+
+    /// GitHub signs the body with an HMAC-SHA256 of the secret, while
+    /// GitLab sends the secret itself as a token.
+    fn verify(&self, source: Source, headers: &HeaderMap, body: &[u8]) -> Result<(), ApiError> {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let verified = match source {
+            Source::GitHub => header("x-hub-signature-256")
+                .and_then(|sig| sig.strip_prefix("sha256="))
+                .is_some_and(|sig| signature::hmac_sha256(&self.secret, &[body], sig)),
+            Source::GitLab => header("x-gitlab-token").is_some_and(|token| {
+                signature::constant_time_eq(token.as_bytes(), self.secret.as_bytes())
+            }),
+        };
+        if verified {
+            Ok(())
+        } else {
+            Err(ApiError::invalid_signature())
+        }
+    }
+
+    fn respond(&self, req: &mut HttpRequest, body: &[u8]) -> Result<HttpResponse, ApiError> {
+        let (source, event) = event(req.headers())?;
+        self.verify(source, req.headers(), body)?;
+        // Pings and other events are acknowledged so the forge doesn't
+        // report the hook as failing.
+        if !is_push(source, event) {
+            return Ok(HttpResponse::NoContent().finish());
+        }
+
+        let params: Params = serde_urlencoded::from_str(req.query_string())
+            .map_err(|e| ApiError::invalid_query(&e.to_string()))?;
+        let format = Format::from_param(params.format.as_deref())?;
+        let push: Push =
+            serde_json::from_slice(body).map_err(|e| ApiError::invalid_body(&e.to_string()))?;
+        let summary = summarize(source, push, *format)?;
+
+        let names: Vec<&str> = summary.commits.iter().map(|c| c.name.as_str()).collect();
+        access::shas(req, names.len());
+        access::names(req, &names);
+        if let Some(ref url) = self.forward {
+            forward(url, &summary, access::logger(req));
+        }
+        Ok(HttpResponse::Ok().json(Response::new(summary)))
+    }
+}
+
+/// Posts the summary to the forwarding url in the background. Failures are
+/// only logged since the forge has nothing to do with them.
+fn forward(url: &str, summary: &Summary, log: Logger) {
+    let request = client::post(url).timeout(FORWARD_TIMEOUT).json(summary);
+    let url = url.to_string();
+    let failed = move |err: &dyn Display| {
+        log.error(&format!("Unable to forward webhook to {}: {}", url, err))
+    };
+    match request {
+        Ok(request) => Arbiter::handle().spawn(request.send().then(move |result| {
+            match result {
+                Ok(ref resp) if resp.status().is_success() => {}
+                Ok(resp) => failed(&resp.status()),
+                Err(err) => failed(&err),
+            }
+            Ok(())
+        })),
+        Err(err) => failed(&err),
+    }
+}
+
+pub fn handler(hook: &Webhook, req: HttpRequest) -> FutureResponse<HttpResponse> {
+    let hook = hook.clone();
+    let mut named = req.clone();
+    let limit = Limits::of(&req).body;
+    req.body()
+        .limit(limit)
+        .map_err(move |err| match err {
+            PayloadError::Overflow => ApiError::body_too_large(limit),
+            err => ApiError::invalid_body(&err.to_string()),
+        })
+        .and_then(move |body| hook.respond(&mut named, &body))
+        .map_err(Error::from)
+        .responder()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    const GITHUB_PUSH: &str = include_str!("../tests/fixtures/github_push.json");
+    const GITHUB_TAG: &str = include_str!("../tests/fixtures/github_tag.json");
+    const GITLAB_PUSH: &str = include_str!("../tests/fixtures/gitlab_push.json");
+    const GITLAB_TAG: &str = include_str!("../tests/fixtures/gitlab_tag.json");
+
+    fn hook() -> Webhook {
+        Webhook::new(String::from("It's a Secret to Everybody"), None)
+    }
+
+    fn summary(source: Source, payload: &str) -> Summary {
+        summarize(source, serde_json::from_str(payload).unwrap(), Case::Lower).unwrap()
+    }
+
+    #[test]
+    fn github_signatures_are_verified() {
+        // The example from GitHub's documentation on validating deliveries.
+        let req = TestRequest::with_header(
+            "X-Hub-Signature-256",
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+        )
+        .finish();
+        let hook = hook();
+        assert!(hook
+            .verify(Source::GitHub, req.headers(), b"Hello, World!")
+            .is_ok());
+        assert_eq!(
+            hook.verify(Source::GitHub, req.headers(), b"Hello, World?"),
+            Err(ApiError::invalid_signature())
+        );
+        let unsigned = TestRequest::default().finish();
+        assert!(hook
+            .verify(Source::GitHub, unsigned.headers(), b"Hello, World!")
+            .is_err());
+    }
+
+    #[test]
+    fn gitlab_tokens_are_verified() {
+        let hook = hook();
+        let req = TestRequest::with_header("X-Gitlab-Token", "It's a Secret to Everybody").finish();
+        assert!(hook.verify(Source::GitLab, req.headers(), b"").is_ok());
+        let req = TestRequest::with_header("X-Gitlab-Token", "It's a Secret").finish();
+        assert!(hook.verify(Source::GitLab, req.headers(), b"").is_err());
+    }
+
+    #[test]
+    fn github_pushes_name_every_commit() {
+        let summary = summary(Source::GitHub, GITHUB_PUSH);
+        assert_eq!(summary.repository, "choubacha/git-release-name");
+        assert_eq!(summary.commits.len(), 2);
+        assert_eq!(summary.commits[0].name, "contemptibly gelid portent");
+        assert_eq!(summary.commits[0].message, "Trim whitespace around shas");
+        assert_eq!(summary.commits[1].name, "intentionally mirky swineherds");
+        assert_eq!(
+            summary.text,
+            "choubacha/git-release-name master: contemptibly gelid portent (8d260e6), \
+             intentionally mirky swineherds (a967711)"
+        );
+    }
+
+    #[test]
+    fn tags_name_the_tagged_commit() {
+        let github = summary(Source::GitHub, GITHUB_TAG);
+        assert_eq!(github.commits.len(), 1);
+        assert_eq!(github.commits[0].name, "intentionally mirky swineherds");
+        assert!(github
+            .text
+            .starts_with("choubacha/git-release-name v0.2.0: "));
+
+        let gitlab = summary(Source::GitLab, GITLAB_TAG);
+        assert_eq!(gitlab.repository, "jsmith/example");
+        assert_eq!(
+            gitlab.commits[0].sha,
+            "82b3d5ae55f7080f1e6022629cdb57bfae7cccc7"
+        );
+    }
+
+    #[test]
+    fn gitlab_pushes_name_every_commit() {
+        let summary = summary(Source::GitLab, GITLAB_PUSH);
+        assert_eq!(summary.repository, "mike/diaspora");
+        assert_eq!(summary.commits.len(), 2);
+        assert_eq!(
+            summary.commits[1].url.as_deref(),
+            Some(
+                "http://example.com/mike/diaspora/commit/da1560886d4f094c3e6c9ef40349f7d38b5d27d7"
+            )
+        );
+    }
+
+    #[test]
+    fn other_events_are_acknowledged() {
+        let mut req = TestRequest::with_header("X-Gitlab-Event", "Issue Hook")
+            .header("X-Gitlab-Token", "It's a Secret to Everybody")
+            .finish();
+        let resp = hook().respond(&mut req, b"{}").unwrap();
+        assert_eq!(resp.status(), ::actix_web::http::StatusCode::NO_CONTENT);
+
+        let mut unsigned = TestRequest::with_header("X-Gitlab-Event", "Push Hook").finish();
+        assert_eq!(
+            hook().respond(&mut unsigned, GITLAB_PUSH.as_bytes()).err(),
+            Some(ApiError::invalid_signature())
+        );
+        let mut unknown = TestRequest::default().finish();
+        assert!(hook().respond(&mut unknown, b"{}").is_err());
+    }
+}